@@ -0,0 +1,2 @@
+-- URLs are deferred, e.g. on HTTP 429 or unreachable robots.txt
+ALTER TABLE url ADD COLUMN not_before TIMESTAMPTZ;
@@ -0,0 +1,2 @@
+DROP TABLE crawl_job_url;
+DROP TABLE crawl_job;
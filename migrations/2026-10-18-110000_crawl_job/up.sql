@@ -0,0 +1,25 @@
+CREATE TABLE crawl_job (
+  crawl_job_id INTEGER GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+  seeds TEXT[] NOT NULL,
+  -- Allowed urls: a host with an optional port, optionally followed by a
+  -- path prefix, e.g. "example.org", "example.org:8080" or "example.org/blog"
+  scope TEXT[] NOT NULL,
+  max_depth SMALLINT DEFAULT 10 NOT NULL,
+  created TIMESTAMPTZ DEFAULT now() NOT NULL,
+  started TIMESTAMPTZ,
+  finished TIMESTAMPTZ,
+  -- crawler instance working on the job
+  lease_owner TEXT,
+  lease_expires TIMESTAMPTZ
+);
+
+-- The urls of each crawl job. A url found by several jobs is crawled by
+-- each of them, with the depth and priority of that job.
+CREATE TABLE crawl_job_url (
+  crawl_job_id INTEGER NOT NULL REFERENCES crawl_job ON DELETE CASCADE,
+  url_id INTEGER NOT NULL REFERENCES url ON DELETE CASCADE,
+  crawl_depth SMALLINT NOT NULL,
+  crawl_priority REAL NOT NULL,
+  PRIMARY KEY (crawl_job_id, url_id)
+);
+CREATE INDEX crawl_job_url_url_id ON crawl_job_url (url_id);
@@ -0,0 +1,2 @@
+ALTER TABLE url DROP COLUMN revisit_interval;
+DROP TABLE fetch_log;
@@ -0,0 +1,26 @@
+-- One row per HTTP request for a url. ("fetch" is a reserved word in SQL.)
+CREATE TABLE fetch_log (
+  fetch_log_id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+  -- NULL after the url was deleted, the fetch is kept
+  url_id INTEGER REFERENCES url ON DELETE SET NULL,
+  fetched TIMESTAMPTZ NOT NULL,
+  status SMALLINT, -- NULL if there was no response
+  duration_ms INTEGER NOT NULL,
+  content_type TEXT,
+  body_size INTEGER,
+  warc_filename TEXT,
+  warc_offset BIGINT,
+  error_class TEXT, -- e.g. timeout, dns, connection, tls
+  -- SHA-1 of the HTTP payload, as in WARC-Payload-Digest, e.g. sha1:AB...
+  payload_digest TEXT,
+  -- warc_offset and warc_length locate the gzip member of the record
+  warc_length BIGINT,
+  -- The url as fetched, the url table knows neither scheme nor port
+  target_uri TEXT
+);
+CREATE INDEX fetch_log_url_id_fetched ON fetch_log (url_id, fetched);
+-- Lookup of earlier captures of the same payload for deduplication
+CREATE INDEX fetch_log_payload_digest ON fetch_log (payload_digest);
+
+-- urls are not fetched again before the revisit interval passed
+ALTER TABLE url ADD COLUMN revisit_interval INTERVAL DEFAULT '7 days' NOT NULL;
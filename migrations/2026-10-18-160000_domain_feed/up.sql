@@ -0,0 +1,9 @@
+-- Syndication feeds offered by a domain, discovered in the HTML head or
+-- found at a conventional location like /feed. The feed itself may be
+-- hosted on another domain.
+CREATE TABLE domain_feed (
+  domain_id INTEGER NOT NULL REFERENCES domain ON DELETE CASCADE,
+  url TEXT NOT NULL,
+  discovered TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (domain_id, url)
+);
@@ -0,0 +1,33 @@
+-- Subscribed feeds, polled every poll_interval
+CREATE TABLE feed (
+  feed_id INTEGER GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+  url TEXT NOT NULL UNIQUE,
+  title TEXT,
+  home_url TEXT,
+  poll_interval INTERVAL NOT NULL DEFAULT '1 hour',
+  next_poll TIMESTAMPTZ NOT NULL DEFAULT now(),
+  last_fetched TIMESTAMPTZ,
+  http_etag TEXT,
+  http_last_modified TIMESTAMPTZ,
+  created TIMESTAMPTZ NOT NULL DEFAULT now(),
+  -- Standing crawl job archiving the pages of new items
+  crawl_job_id INTEGER REFERENCES crawl_job ON DELETE SET NULL
+);
+CREATE INDEX feed_next_poll ON feed (next_poll);
+
+CREATE TABLE feed_item (
+  feed_item_id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+  feed_id INTEGER NOT NULL REFERENCES feed ON DELETE CASCADE,
+  guid TEXT NOT NULL, -- id of the item, its link if it has no id
+  link TEXT,
+  title TEXT,
+  author TEXT,
+  published TIMESTAMPTZ,
+  updated TIMESTAMPTZ,
+  content TEXT,
+  fetched TIMESTAMPTZ NOT NULL DEFAULT now(),
+  read BOOLEAN NOT NULL DEFAULT false,
+  starred BOOLEAN NOT NULL DEFAULT false,
+  UNIQUE (feed_id, guid)
+);
+CREATE INDEX feed_item_feed_id_link ON feed_item (feed_id, link);
@@ -0,0 +1,2 @@
+-- Path of nested categories separated by slashes, e.g. Tech/Rust
+ALTER TABLE feed ADD COLUMN category TEXT;
@@ -9,6 +9,11 @@ pub fn elapsed(since: SystemTime, seconds: u64) -> bool {
     duration.as_secs() > seconds
 }
 
+/// Duration from now until the given time, zero if already elapsed
+pub fn until(until: SystemTime) -> Duration {
+    until.duration_since(SystemTime::now()).unwrap_or_default()
+}
+
 /// Waits or returns immediately if until already elapsed.
 pub fn wait(until: SystemTime) {
     let now = SystemTime::now();
@@ -18,5 +23,5 @@ pub fn wait(until: SystemTime) {
             debug!("Waiting for {} ms.", d.as_millis());
             std::thread::sleep(d);
         }
-    };
+    }
 }
@@ -0,0 +1,177 @@
+//! A crawl job crawls everything reachable from its seed urls within its
+//! scope. Jobs are stored in the database and leased by crawler instances
+//! while they work on them.
+
+use crate::db::{self, models};
+use crate::url_util::host_port;
+use anyhow::{anyhow, Result};
+use url::Url;
+
+const DEFAULT_MAX_DEPTH: i16 = 10;
+
+pub struct CrawlJob {
+    pub id: i32,
+    pub seeds: Vec<Url>,
+    pub scope: Scope,
+    pub max_depth: i16,
+}
+
+impl From<models::CrawlJob> for CrawlJob {
+    fn from(m: models::CrawlJob) -> Self {
+        let seeds = m
+            .seeds
+            .iter()
+            .flatten()
+            .filter_map(|s| match Url::parse(s) {
+                Ok(url) => Some(url),
+                Err(e) => {
+                    warn!("Invalid seed {s} in crawl job {}: {e}", m.crawl_job_id);
+                    None
+                }
+            })
+            .collect();
+        let scope: Vec<String> = m.scope.into_iter().flatten().collect();
+        CrawlJob {
+            id: m.crawl_job_id,
+            seeds,
+            scope: Scope::new(&scope),
+            max_depth: m.max_depth,
+        }
+    }
+}
+
+/// Urls allowed to be crawled. Each rule is a host, optionally with a port,
+/// followed by an optional path prefix, e.g. `example.org`,
+/// `example.org:8080` or `example.org/blog`. A rule without a port matches
+/// the default port of the scheme only. A path prefix matches whole path
+/// segments, `example.org/blog` matches `/blog` and `/blog/1` but not
+/// `/blogroll`.
+#[derive(Debug, Default)]
+pub struct Scope {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug)]
+struct Rule {
+    host: String,
+    port: Option<u16>,
+    prefix: String,
+}
+
+impl Scope {
+    pub fn new(rules: &[String]) -> Self {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                let (authority, prefix) = match rule.find('/') {
+                    Some(pos) => (&rule[..pos], &rule[pos..]),
+                    None => (rule.as_str(), "/"),
+                };
+                let (host, port) = authority
+                    .rsplit_once(':')
+                    .and_then(|(host, port)| Some((host, Some(port.parse().ok()?))))
+                    .unwrap_or((authority, None));
+                Rule {
+                    host: host.to_lowercase(),
+                    port,
+                    prefix: prefix.to_string(),
+                }
+            })
+            .collect();
+        Self { rules }
+    }
+
+    pub fn contains(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let path = url.path();
+        self.rules.iter().any(|rule| {
+            let port = match rule.port {
+                Some(_) => url.port_or_known_default(),
+                None => url.port(),
+            };
+            rule.host == host
+                && port == rule.port
+                && path.starts_with(&rule.prefix)
+                && (rule.prefix.ends_with('/')
+                    || path.len() == rule.prefix.len()
+                    || path[rule.prefix.len()..].starts_with('/'))
+        })
+    }
+}
+
+/// Command line: `add-job [--max-depth N] [--scope RULE]... SEED...`
+///
+/// Without `--scope` the hosts and ports of the seeds are the scope.
+pub fn add(args: &[String]) -> Result<()> {
+    let mut seeds: Vec<Url> = Vec::new();
+    let mut scope: Vec<String> = Vec::new();
+    let mut max_depth = DEFAULT_MAX_DEPTH;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--max-depth" => {
+                let value = args.next().ok_or(anyhow!("--max-depth needs a value"))?;
+                max_depth = value.parse()?;
+            }
+            "--scope" => {
+                let value = args.next().ok_or(anyhow!("--scope needs a value"))?;
+                scope.push(value.clone());
+            }
+            seed => seeds.push(Url::parse(seed)?),
+        }
+    }
+    if seeds.is_empty() {
+        return Err(anyhow!("A crawl job needs at least one seed url"));
+    }
+    if scope.is_empty() {
+        scope = seeds
+            .iter()
+            .filter_map(host_port)
+            .map(String::from)
+            .collect();
+    }
+
+    let seeds: Vec<String> = seeds.iter().map(Url::to_string).collect();
+    let id = db::insert_crawl_job(&mut db::init_conn()?, &seeds, &scope, max_depth)?;
+    info!("Added crawl job {id}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Scope;
+    use url::Url;
+
+    #[test]
+    fn scope_contains() {
+        let scope = Scope::new(&[
+            String::from("example.org"),
+            String::from("Blog.Example.com/posts/"),
+            String::from("example.net/blog"),
+            String::from("localhost:8080"),
+            String::from("example.com:443"),
+        ]);
+        let contains = |s| scope.contains(&Url::parse(s).unwrap());
+
+        assert!(contains("https://example.org"));
+        assert!(contains("http://example.org/a/b?c=d"));
+        assert!(contains("https://blog.example.com/posts/1"));
+        assert!(!contains("https://blog.example.com/about"));
+        assert!(!contains("https://www.example.org/"));
+        assert!(!contains("mailto:info@example.org"));
+        assert!(!contains("https://example.org:8443/"));
+
+        assert!(contains("https://example.net/blog"));
+        assert!(contains("https://example.net/blog/1"));
+        assert!(!contains("https://example.net/blogspam"));
+        assert!(!contains("https://example.net/"));
+
+        assert!(contains("http://localhost:8080/a"));
+        assert!(!contains("http://localhost/a"));
+        assert!(contains("https://example.com/"));
+        assert!(!contains("http://example.com/"));
+    }
+}
@@ -9,22 +9,38 @@
 //! - <https://developers.google.com/search/docs/crawling-indexing/reduce-crawl-rate>
 
 use crate::env_config::BOT_NAME;
-use crate::fetcher::Fetcher;
+use crate::fetcher::{FetchResult, Fetcher, Validators};
 
 use crate::link_extractor::extract_outlinks;
+use crate::link_extractor::feed::is_feed_type;
+use crate::politeness::PolitenessMap;
 use crate::robotstxt::{CheckResult, RobotsTxt};
-use crate::signal_handler::SignalHandler;
-use crate::url_frontier::UrlFrontier;
+use crate::signal_handler::{Grace, SignalHandler};
+use crate::url_frontier::{Next, UrlFrontier};
 use crate::url_util::{is_domain_root, with_path_only};
+use crate::warc;
 use anyhow::Result;
+use http::StatusCode;
+use std::sync::atomic::AtomicU32;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, SystemTime};
 
 use url::Url;
 
+/// Maximum number of redirect hops followed from one URL. Google documents to
+/// follow up to ten hops.
+const MAX_REDIRECTS: usize = 10;
+/// Pause of a worker while all hosts with urls left are busy
+const BUSY_WAIT: Duration = Duration::from_millis(500);
+/// Conventional feed locations tried on domain roots that announce no feed
+const FEED_PATHS: [&str; 2] = ["feed", "rss.xml"];
+
 pub struct Crawler {
     fetcher: Fetcher,
     robotstxt: RobotsTxt,
-    signal_handler: SignalHandler,
-    url_frontier: UrlFrontier,
+    grace: Arc<Grace>,
+    url_frontier: Arc<Mutex<UrlFrontier>>,
 }
 
 /// These contexts are not the ones from [Mime Sniffing
@@ -45,9 +61,6 @@ pub enum Context {
     FeedLink,
     #[default]
     Other,
-    Img,
-    Style,
-    Script,
 }
 
 #[derive(Default, Clone)]
@@ -56,8 +69,11 @@ pub struct Inlink {
     /// or PREV/NEXT, however we're only interested in NEXT
     pub rel: Option<String>,
     pub context: Context,
-    pub _redirect_count: usize,
+    /// Number of redirect hops that lead to this link
+    pub redirect_count: usize,
     pub _content_type: Option<String>,
+    /// Priority given by the linking resource, e.g. `<priority>` in sitemaps
+    pub priority: Option<f32>,
 }
 
 pub struct Outlink {
@@ -68,61 +84,287 @@ pub struct Outlink {
 pub struct UrlItem {
     pub url: Url,
     pub i: Vec<Inlink>,
+    /// Row of the url table, None if not yet stored
+    pub url_id: Option<i32>,
+    /// Cache validators of the last fetch
+    pub validators: Validators,
+    /// Number of hops from the seeds of the crawl job
+    pub depth: i16,
+    /// 0 < priority <= 1, see [`crate::url_frontier::priority`]
+    pub priority: f32,
+}
+
+impl UrlItem {
+    pub fn new(url: Url) -> Self {
+        Self {
+            url,
+            i: vec![],
+            url_id: None,
+            validators: Validators::default(),
+            depth: 0,
+            priority: 1.0,
+        }
+    }
+}
+
+/// Runs the given number of crawler workers on one shared url frontier
+/// until no crawl job is left or the crawl gets interrupted.
+pub fn crawl(workers: usize, signal_handler: &SignalHandler) -> Result<()> {
+    let bot_name = BOT_NAME.get();
+    let grace = Arc::new(signal_handler.grace());
+    let politeness = PolitenessMap::new_shared();
+    let archive_file_cnt = Arc::new(AtomicU32::new(warc::next_file_serial()?));
+    let robotstxt = RobotsTxt::new(&bot_name);
+    let url_frontier = Arc::new(Mutex::new(UrlFrontier::new(Arc::clone(&politeness))?));
+
+    let mut handles = Vec::with_capacity(workers);
+    for i in 0..workers {
+        let mut crawler = Crawler {
+            fetcher: Fetcher::new(
+                &bot_name,
+                Arc::clone(&politeness),
+                Arc::clone(&archive_file_cnt),
+            )?,
+            robotstxt: robotstxt.clone(),
+            grace: Arc::clone(&grace),
+            url_frontier: Arc::clone(&url_frontier),
+        };
+        let handle = thread::Builder::new()
+            .name(format!("crawler-{i}"))
+            .spawn(move || {
+                if let Err(e) = crawler.run() {
+                    error!("{e:?}");
+                }
+            })?;
+        handles.push(handle);
+    }
+
+    info!("waiting for {workers} crawlers");
+    for handle in handles {
+        let _ = handle.join();
+    }
+    Ok(())
 }
 
 impl Crawler {
-    pub fn new(signal_handler: SignalHandler) -> Result<Self> {
-        let bot_name = BOT_NAME.get();
-        Ok(Self {
-            fetcher: Fetcher::new(&bot_name.clone()),
-            robotstxt: RobotsTxt::new(&bot_name),
-            signal_handler,
-            url_frontier: UrlFrontier::new()?,
-        })
+    fn frontier(&self) -> MutexGuard<'_, UrlFrontier> {
+        self.url_frontier.lock().unwrap()
     }
 
     pub fn run(&mut self) -> Result<()> {
-        let grace = self.signal_handler.grace();
-        while let Some(item) = self.url_frontier.get_item()? {
-            let url = &item.url;
-            match self.robotstxt.check(url, &mut self.fetcher)? {
-                CheckResult::Allowed => (),
-                CheckResult::Disallowed => {
-                    info!("Crawling of {url} forbidden by robots.txt");
+        while !self.grace.is_interrupted() {
+            let next = self.frontier().get_item()?;
+            let item = match next {
+                Next::Url(item) => item,
+                Next::Busy => {
+                    thread::sleep(BUSY_WAIT);
                     continue;
                 }
-                CheckResult::Retry(seconds) => {
-                    info!("Retry robots check for {url} in {seconds}s");
-                    todo!("put back url for retry in seconds");
-                    //continue;
+                Next::Wait(until) => {
+                    // Without holding the frontier, other workers may go on
+                    while SystemTime::now() < until && !self.grace.is_interrupted() {
+                        thread::sleep(BUSY_WAIT);
+                    }
+                    continue;
                 }
-            }
+                Next::Done => break,
+            };
+            let (url, url_id) = (item.url.clone(), item.url_id);
+            let job_id = self.frontier().job_id();
+            self.fetcher.set_crawl_job(job_id)?;
+            let result = self.crawl_item(item);
+            let mut frontier = self.frontier();
+            let result = match result {
+                Err(e) if !is_db_error(&e) => {
+                    error!("Crawling {url} failed: {e:?}");
+                    url_id.map_or(Ok(()), |id| frontier.skip(id))
+                }
+                result => result,
+            };
+            frontier.release(&url, url_id);
+            result?;
+        }
+        Ok(())
+    }
 
-            let fr = self.fetcher.fetch(&item.url.clone())?;
-            let mut outlinks = extract_outlinks(&item, &fr)?;
-            debug!("extracted {} outlinks from {url}", outlinks.len());
-            if is_domain_root(url) {
-                debug!("Adding sitemap outlinks for domain root: {url}");
-                let mut sitemap_outlinks = self.robotstxt.get_sitemaps(url, &mut self.fetcher)?;
-                if sitemap_outlinks.is_empty() {
-                    sitemap_outlinks = vec![Outlink {
-                        url: with_path_only(url, "sitemap.xml"),
-                        i: Inlink {
-                            context: Context::Sitemap,
-                            ..Inlink::default()
-                        },
-                    }];
+    fn crawl_item(&mut self, item: UrlItem) -> Result<()> {
+        let url = &item.url;
+        match self.robotstxt.check(url, &mut self.fetcher)? {
+            CheckResult::Allowed => (),
+            CheckResult::Disallowed => {
+                info!("Crawling of {url} forbidden by robots.txt");
+                if let Some(id) = item.url_id {
+                    self.frontier().skip(id)?;
                 }
-                outlinks.append(&mut sitemap_outlinks);
+                return Ok(());
             }
-            let outlinks = self.robotstxt.filter_outlinks(outlinks, &mut self.fetcher);
-
-            self.url_frontier.put_outlinks(&item.url, &outlinks)?;
+            CheckResult::Retry(seconds) => {
+                info!("Retry robots check for {url} in {seconds}s");
+                let not_before = SystemTime::now() + Duration::from_secs(seconds);
+                self.frontier().put_back(&item, not_before)?;
+                return Ok(());
+            }
+        }
 
-            if grace.is_interrupted() {
-                break;
+        let fr = match self.fetcher.fetch_conditional(url, &item.validators) {
+            Ok(fr) => fr,
+            Err(e) => {
+                info!("Fetching {} failed: {e}", item.url);
+                self.frontier().put_failed(&item)?;
+                return Ok(());
             }
+        };
+        let Some((item, fr)) = self.follow_redirects(item, fr)? else {
+            return Ok(());
+        };
+        if fr.is_throttled() {
+            let retry_at = fr.retry_after.unwrap_or(fr.start);
+            info!("Got status {} for {}, putting it back", fr.status, item.url);
+            self.frontier().put_back(&item, retry_at)?;
+            return Ok(());
+        }
+        if fr.is_unchanged() {
+            debug!("Unchanged since last fetch: {}", item.url);
+            return Ok(());
+        }
+        if fr.status == StatusCode::OK {
+            self.frontier().put_validators(&item, &fr)?;
+        }
+        let url = &item.url;
+        let mut outlinks = extract_outlinks(&item, &fr)?;
+        debug!("extracted {} outlinks from {url}", outlinks.len());
+        let feeds = announced_feeds(&outlinks);
+        if !feeds.is_empty() {
+            self.frontier().put_feeds(url, &feeds)?;
+        } else if is_guessed_feed(url, &fr) {
+            self.frontier().put_feeds(url, &[url])?;
+        }
+        if is_domain_root(url) && feeds.is_empty() {
+            outlinks.extend(FEED_PATHS.iter().map(|path| Outlink {
+                url: with_path_only(url, path),
+                i: Inlink {
+                    context: Context::Feed,
+                    ..Inlink::default()
+                },
+            }));
         }
+        if is_domain_root(url) {
+            debug!("Adding sitemap outlinks for domain root: {url}");
+            let mut sitemap_outlinks = self.robotstxt.get_sitemaps(url, &mut self.fetcher)?;
+            if sitemap_outlinks.is_empty() {
+                sitemap_outlinks = vec![Outlink {
+                    url: with_path_only(url, "sitemap.xml"),
+                    i: Inlink {
+                        context: Context::Sitemap,
+                        ..Inlink::default()
+                    },
+                }];
+            }
+            outlinks.append(&mut sitemap_outlinks);
+        }
+        {
+            let frontier = self.frontier();
+            outlinks.retain(|o| frontier.in_scope(&o.url));
+        }
+        let outlinks = self.robotstxt.filter_outlinks(outlinks, &mut self.fetcher);
+
+        self.frontier().put_outlinks(&item, &outlinks)?;
         Ok(())
     }
+
+    /// Follows the `Location` of redirect responses hop by hop. Each hop is
+    /// fetched and archived on its own. A chain of only permanent redirects
+    /// replaces the original URL with the final target in the frontier.
+    ///
+    /// Returns None if the chain could not be followed to its end.
+    fn follow_redirects(
+        &mut self,
+        mut item: UrlItem,
+        mut fr: FetchResult,
+    ) -> Result<Option<(UrlItem, FetchResult)>> {
+        let origin = item.url.clone();
+        let mut permanent = true;
+
+        while let Some(target) = fr.redirect_target().cloned() {
+            permanent &= fr.is_permanent_redirect();
+            let mut inlink = item.i.first().cloned().unwrap_or_default();
+            inlink.redirect_count += 1;
+            if inlink.redirect_count > MAX_REDIRECTS {
+                info!("Giving up on {origin} after {MAX_REDIRECTS} redirects");
+                return Ok(None);
+            }
+
+            if !self.frontier().in_scope(&target) {
+                info!(
+                    "Redirect from {} to {target} leaves the crawl scope",
+                    item.url
+                );
+                return Ok(None);
+            }
+            // Also on the same host, robots.txt may disallow only the target path
+            match self.robotstxt.check(&target, &mut self.fetcher)? {
+                CheckResult::Allowed => (),
+                CheckResult::Disallowed => {
+                    info!(
+                        "Redirect from {} to {target} forbidden by robots.txt",
+                        item.url
+                    );
+                    return Ok(None);
+                }
+                CheckResult::Retry(seconds) => {
+                    info!(
+                        "Redirect from {} to {target} needs robots check in {seconds}s",
+                        item.url
+                    );
+                    let not_before = SystemTime::now() + Duration::from_secs(seconds);
+                    self.frontier()
+                        .put_back(&UrlItem::new(target), not_before)?;
+                    return Ok(None);
+                }
+            }
+
+            debug!("Following redirect {} -> {target}", item.url);
+            fr = self.fetcher.fetch(&target)?;
+            item = UrlItem {
+                i: vec![inlink],
+                depth: item.depth,
+                priority: item.priority,
+                ..UrlItem::new(target)
+            };
+        }
+
+        if permanent && item.url != origin {
+            self.frontier().put_redirect(&origin, &item.url)?;
+        }
+        if item.url != origin {
+            // The validators of the final response are stored with the target
+            item.url_id = Some(self.frontier().url_id(&item.url)?);
+        }
+        Ok(Some((item, fr)))
+    }
+}
+
+/// Feeds announced by `<link rel="alternate">` in the HTML head
+fn announced_feeds(outlinks: &[Outlink]) -> Vec<&Url> {
+    outlinks
+        .iter()
+        .filter(|o| matches!(o.i.context, Context::Feed) && o.i.rel.as_deref() == Some("alternate"))
+        .map(|o| &o.url)
+        .collect()
+}
+
+/// A feed found at one of the [`FEED_PATHS`]
+fn is_guessed_feed(url: &Url, fr: &FetchResult) -> bool {
+    fr.status == StatusCode::OK
+        && fr.content_type.as_deref().is_some_and(is_feed_type)
+        && url
+            .path()
+            .strip_prefix('/')
+            .is_some_and(|path| FEED_PATHS.contains(&path))
+}
+
+/// Errors of the database end a worker, other errors only the crawl of an item
+fn is_db_error(e: &anyhow::Error) -> bool {
+    e.chain()
+        .any(|c| c.is::<diesel::result::Error>() || c.is::<diesel::result::ConnectionError>())
 }
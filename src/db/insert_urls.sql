@@ -1,22 +1,39 @@
-WITH sel AS (
-   SELECT val.domain_name, val.path, val.query, d.domain_id AS domain_id
+WITH val AS (
+   SELECT val.domain_name, val.path, val.query, val.crawl_job_id, val.crawl_depth, val.crawl_priority,
+     d.domain_id AS domain_id
    FROM  (
       VALUES {}
-      ) val (domain_name, path, query)
+      ) val (domain_name, path, query, crawl_job_id, crawl_depth, crawl_priority)
    LEFT JOIN domain d ON (domain_name = d.name)
    )
 , ins AS (
    INSERT INTO domain (name)
-   SELECT DISTINCT domain_name FROM sel WHERE domain_id IS NULL
+   SELECT DISTINCT domain_name FROM val WHERE domain_id IS NULL
    RETURNING domain_id, name as domain_name
    )
-INSERT INTO url (domain_id, path, query)
-  SELECT
-    COALESCE(sel.domain_id, ins.domain_id),
-    sel.path,
-    sel.query
+, sel AS (
+   SELECT COALESCE(val.domain_id, ins.domain_id) AS domain_id, path, query,
+     crawl_job_id, crawl_depth, crawl_priority
+   FROM val
+   LEFT JOIN ins USING (domain_name)
+   )
+, u AS (
+   INSERT INTO url (domain_id, path, query, crawl_depth, crawl_priority)
+     SELECT domain_id, path, query, crawl_depth, crawl_priority FROM sel
+   -- a url found again on a shorter or better path
+   ON CONFLICT (domain_id, path, query) DO UPDATE SET
+     crawl_depth = LEAST(url.crawl_depth, EXCLUDED.crawl_depth),
+     crawl_priority = GREATEST(url.crawl_priority, EXCLUDED.crawl_priority)
+   RETURNING url_id, domain_id, path, query
+   )
+INSERT INTO crawl_job_url (crawl_job_id, url_id, crawl_depth, crawl_priority)
+  SELECT sel.crawl_job_id, u.url_id, sel.crawl_depth, sel.crawl_priority
   FROM sel
-  LEFT JOIN ins USING (domain_name)
-ON CONFLICT DO NOTHING
--- RETURNING url_id as id
+  JOIN u ON u.domain_id = sel.domain_id
+    AND u.path = sel.path
+    AND u.query IS NOT DISTINCT FROM sel.query
+-- a url found again by the same job
+ON CONFLICT (crawl_job_id, url_id) DO UPDATE SET
+  crawl_depth = LEAST(crawl_job_url.crawl_depth, EXCLUDED.crawl_depth),
+  crawl_priority = GREATEST(crawl_job_url.crawl_priority, EXCLUDED.crawl_priority)
 ;
@@ -0,0 +1,15 @@
+UPDATE crawl_job SET
+  lease_owner = $1,
+  lease_expires = now() + make_interval(secs => $2),
+  started = COALESCE(started, now())
+WHERE crawl_job_id = (
+   SELECT crawl_job_id FROM crawl_job
+   WHERE finished IS NULL
+     AND (lease_expires IS NULL OR lease_expires < now())
+     AND crawl_job_id <> ALL ($3)
+   ORDER BY created
+   LIMIT 1
+   FOR UPDATE SKIP LOCKED
+   )
+RETURNING *
+;
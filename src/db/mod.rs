@@ -1,6 +1,12 @@
 use crate::env_config::DB_URL;
-use anyhow::Result;
+use crate::fetcher::Validators;
+use crate::link_extractor::feed::FeedItem;
+use crate::url_util;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
 use diesel::prelude::*;
+use std::collections::BTreeMap;
+use std::time::Duration;
 use url::Url;
 
 pub mod models;
@@ -29,46 +35,636 @@ pub fn format_bind_params(rows: usize, columns: usize) -> String {
         .join(",")
 }
 
+/// Name of the url's row in the domain table, the host including IP
+/// addresses
+fn domain_name(url: &Url) -> Result<String> {
+    url.host_str()
+        .map(String::from)
+        .ok_or_else(|| anyhow!("No host in {url}"))
+}
+
+/// A url to be inserted into the frontier
+pub struct NewUrl<'a> {
+    pub url: &'a Url,
+    pub depth: i16,
+    pub priority: f32,
+}
+
+/// Adds the urls to the crawl job. Urls known from other jobs are crawled
+/// again by this job once their revisit interval passed.
+///
 /// Query idea found here:
 /// <https://dba.stackexchange.com/questions/46410/how-do-i-insert-a-row-which-contains-a-foreign-key>
-pub fn insert_urls(conn: &mut PgConnection, urls: &Vec<Url>) -> Result<usize> {
-    use diesel::sql_types::{Nullable, Text};
+///
+/// Urls differing only in scheme or port share a row. They are merged with
+/// the lowest depth and the highest priority, Postgres can not update the same
+/// row twice in one statement.
+pub fn insert_urls(conn: &mut PgConnection, crawl_job_id: i32, urls: &[NewUrl]) -> Result<usize> {
+    use diesel::sql_types::{Float, Integer, Nullable, SmallInt, Text};
 
+    if urls.is_empty() {
+        return Ok(0);
+    }
+    let mut rows: BTreeMap<(String, &str, Option<&str>), (i16, f32)> = BTreeMap::new();
+    for new_url in urls {
+        let url = new_url.url;
+        rows.entry((domain_name(url)?, url.path(), url.query()))
+            .and_modify(|(depth, priority)| {
+                *depth = (*depth).min(new_url.depth);
+                *priority = priority.max(new_url.priority);
+            })
+            .or_insert((new_url.depth, new_url.priority));
+    }
     let q_str = format!(
         include_str!("insert_urls.sql"),
-        format_bind_params(urls.len(), 3)
+        format_bind_params(rows.len(), 6)
     );
 
     let mut q = diesel::sql_query(q_str).into_boxed();
-    for url in urls {
+    for ((domain_name, path, query), (depth, priority)) in rows {
         q = q
-            .bind::<Text, _>(url.domain().unwrap().to_string())
-            .bind::<Text, _>(url.path().to_string())
-            .bind::<Nullable<Text>, _>(url.query().map(String::from));
+            .bind::<Text, _>(domain_name)
+            .bind::<Text, _>(path.to_string())
+            .bind::<Nullable<Text>, _>(query.map(String::from))
+            .bind::<Integer, _>(crawl_job_id)
+            .bind::<SmallInt, _>(depth)
+            .bind::<Float, _>(priority);
     }
     let affected = q.execute(conn)?;
     debug!("insert_urls got {} urls, inserted {affected}", urls.len());
     Ok(affected)
 }
 
+/// Moves the crawl jobs of `from` with their depth and priority to `to`.
+/// The row of `from` is kept, so the url is not fetched again within its
+/// revisit interval when it is linked again.
+pub fn replace_url(conn: &mut PgConnection, from: &Url, to: &Url) -> Result<()> {
+    use diesel::sql_types::{Integer, Nullable, Text};
+
+    if (from.host_str(), from.path(), from.query()) == (to.host_str(), to.path(), to.query()) {
+        // e.g. redirect from http to https, the url table does not know the scheme
+        return Ok(());
+    }
+
+    conn.transaction(|conn| {
+        let to_id = select_url_id(conn, to)?;
+        let affected = diesel::sql_query(include_str!("replace_url.sql"))
+            .bind::<Text, _>(domain_name(from)?)
+            .bind::<Text, _>(from.path())
+            .bind::<Nullable<Text>, _>(from.query())
+            .bind::<Integer, _>(to_id)
+            .execute(conn)?;
+        debug!("replace_url {from} -> {to} moved {affected} crawl job urls");
+        Ok(())
+    })
+}
+
+/// Id of the url's row, the row is inserted if the url is unknown
+pub fn select_url_id(conn: &mut PgConnection, url: &Url) -> Result<i32> {
+    use diesel::sql_types::{Nullable, Text};
+
+    conn.transaction(|conn| {
+        diesel::sql_query("INSERT INTO domain (name) VALUES ($1) ON CONFLICT DO NOTHING")
+            .bind::<Text, _>(domain_name(url)?)
+            .execute(conn)?;
+        let id: Id = diesel::sql_query(include_str!("select_url_id.sql"))
+            .bind::<Text, _>(domain_name(url)?)
+            .bind::<Text, _>(url.path())
+            .bind::<Nullable<Text>, _>(url.query())
+            .get_result(conn)?;
+        Ok(id.0)
+    })
+}
+
+/// Records feeds offered by the domain of `site`
+pub fn insert_domain_feeds(conn: &mut PgConnection, site: &Url, feeds: &[&Url]) -> Result<()> {
+    use diesel::sql_types::{Array, Text};
+
+    if feeds.is_empty() {
+        return Ok(());
+    }
+    let feeds: Vec<&str> = feeds.iter().map(|f| f.as_str()).collect();
+    conn.transaction(|conn| {
+        diesel::sql_query("INSERT INTO domain (name) VALUES ($1) ON CONFLICT DO NOTHING")
+            .bind::<Text, _>(domain_name(site)?)
+            .execute(conn)?;
+        let affected = diesel::sql_query(
+            "INSERT INTO domain_feed (domain_id, url)
+             SELECT domain_id, unnest($2) FROM domain WHERE name = $1
+             ON CONFLICT DO NOTHING",
+        )
+        .bind::<Text, _>(domain_name(site)?)
+        .bind::<Array<Text>, _>(&feeds)
+        .execute(conn)?;
+        debug!("insert_domain_feeds for {site} inserted {affected} feeds");
+        Ok(())
+    })
+}
+
+pub fn update_validators(conn: &mut PgConnection, id: i32, validators: &Validators) -> Result<()> {
+    use crate::db::schema::url::dsl::{http_etag, http_last_modified, url};
+
+    let last_modified: Option<DateTime<Utc>> = validators.last_modified.map(Into::into);
+    diesel::update(url.find(id))
+        .set((
+            http_etag.eq(&validators.etag),
+            http_last_modified.eq(last_modified),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn defer_url(conn: &mut PgConnection, url: &Url, not_before: DateTime<Utc>) -> Result<()> {
+    use diesel::sql_types::{Nullable, Text, Timestamptz};
+
+    diesel::sql_query(
+        "UPDATE url SET not_before = $4 FROM domain
+         WHERE url.domain_id = domain.domain_id
+           AND name = $1 AND path = $2 AND query IS NOT DISTINCT FROM $3",
+    )
+    .bind::<Text, _>(domain_name(url)?)
+    .bind::<Text, _>(url.path().to_string())
+    .bind::<Nullable<Text>, _>(url.query().map(String::from))
+    .bind::<Timestamptz, _>(not_before)
+    .execute(conn)?;
+    Ok(())
+}
+
+/// Priority 0 excludes the url from the crawl job, unless it is found again
+pub fn skip_crawl_url(conn: &mut PgConnection, job_id: i32, url_id: i32) -> Result<()> {
+    use crate::db::schema::crawl_job_url::dsl;
+
+    diesel::update(dsl::crawl_job_url.find((job_id, url_id)))
+        .set(dsl::crawl_priority.eq(0.0))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Number of failed fetches of the url since its last successful fetch
+pub fn count_fetch_errors(conn: &mut PgConnection, id: i32) -> Result<i64> {
+    use crate::db::schema::fetch_log::dsl::{error_class, fetch_log, fetched, url_id};
+
+    let last_success: Option<DateTime<Utc>> = fetch_log
+        .filter(url_id.eq(id))
+        .filter(error_class.is_null())
+        .select(diesel::dsl::max(fetched))
+        .first(conn)?;
+    let mut errors = fetch_log
+        .filter(url_id.eq(id))
+        .filter(error_class.is_not_null())
+        .into_boxed();
+    if let Some(last_success) = last_success {
+        errors = errors.filter(fetched.gt(last_success));
+    }
+    Ok(errors.count().get_result(conn)?)
+}
+
+/// Earliest time a deferred url of the crawl job may be crawled
+pub fn select_next_not_before(
+    conn: &mut PgConnection,
+    job_id: i32,
+) -> Result<Option<DateTime<Utc>>> {
+    use crate::db::schema::{crawl_job_url, url};
+    use diesel::dsl::now;
+
+    Ok(url::table
+        .inner_join(crawl_job_url::table)
+        .filter(crawl_job_url::crawl_job_id.eq(job_id))
+        .filter(url::not_before.gt(now))
+        .select(diesel::dsl::min(url::not_before))
+        .first(conn)?)
+}
+
+/// Selects the urls with the highest priority of each domain of the crawl
+/// job, at most `per_domain` urls for each domain. Urls fetched successfully
+/// within their revisit interval and deferred urls are left out.
 pub fn select_crawl_urls(
     conn: &mut PgConnection,
-    domain: &str,
-    exclude_url_ids: &Vec<i32>,
+    job_id: i32,
+    max_depth: i16,
+    exclude_url_ids: &[i32],
+    exclude_domains: &[&str],
+    per_domain: i32,
 ) -> Result<Vec<(models::Url, models::Domain)>> {
-    use crate::db::schema::domain::dsl;
-    use crate::db::schema::url::dsl::{url, url_id};
-    use diesel::dsl::not;
-
-    Ok(url
-        .inner_join(dsl::domain)
-        .filter(dsl::name.eq(domain))
-        .filter(not(url_id.eq_any(exclude_url_ids)))
-        .select((models::Url::as_select(), models::Domain::as_select()))
-        .limit(10)
+    use diesel::sql_types::{Array, Integer, SmallInt, Text};
+
+    let rows: Vec<models::CrawlUrl> = diesel::sql_query(include_str!("select_crawl_urls.sql"))
+        .bind::<Integer, _>(job_id)
+        .bind::<SmallInt, _>(max_depth)
+        .bind::<Array<Integer>, _>(exclude_url_ids)
+        .bind::<Integer, _>(per_domain)
+        .bind::<Array<Text>, _>(exclude_domains)
+        .load(conn)?;
+    Ok(rows.into_iter().map(|r| (r.url, r.domain)).collect())
+}
+
+/// URL and time of the first archived fetch with the given payload digest
+pub fn select_first_capture(
+    conn: &mut PgConnection,
+    digest: &str,
+) -> Result<Option<(Url, DateTime<Utc>)>> {
+    use crate::db::schema::{domain, fetch_log, url};
+
+    type Row = (
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        DateTime<Utc>,
+    );
+    let row: Option<Row> = fetch_log::table
+        .left_join(url::table.inner_join(domain::table))
+        .filter(fetch_log::payload_digest.eq(digest))
+        .filter(fetch_log::warc_filename.is_not_null())
+        .order(fetch_log::fetched.asc())
+        .select((
+            fetch_log::target_uri,
+            domain::name.nullable(),
+            url::path.nullable(),
+            url::query.nullable(),
+            fetch_log::fetched,
+        ))
+        .first(conn)
+        .optional()?;
+    Ok(
+        row.and_then(|(target_uri, domain_name, path, query, fetched)| {
+            let url = match target_uri.and_then(|u| Url::parse(&u).ok()) {
+                Some(url) => url,
+                // Rows logged before target_uri existed only know host, path and query
+                None => url_util::build(&domain_name?, &path?, query.as_deref()),
+            };
+            Some((url, fetched))
+        }),
+    )
+}
+
+pub fn insert_fetch_log(conn: &mut PgConnection, fetch_log: &models::NewFetchLog) -> Result<()> {
+    diesel::insert_into(schema::fetch_log::table)
+        .values(fetch_log)
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn insert_crawl_job(
+    conn: &mut PgConnection,
+    seeds: &[String],
+    scope: &[String],
+    max_depth: i16,
+) -> Result<i32> {
+    use crate::db::schema::crawl_job::dsl;
+
+    Ok(diesel::insert_into(dsl::crawl_job)
+        .values((
+            dsl::seeds.eq(seeds),
+            dsl::scope.eq(scope),
+            dsl::max_depth.eq(max_depth),
+        ))
+        .returning(dsl::crawl_job_id)
+        .get_result(conn)?)
+}
+
+pub fn select_crawl_job(conn: &mut PgConnection, job_id: i32) -> Result<Option<models::CrawlJob>> {
+    use crate::db::schema::crawl_job::dsl;
+
+    Ok(dsl::crawl_job
+        .find(job_id)
+        .select(models::CrawlJob::as_select())
+        .first(conn)
+        .optional()?)
+}
+
+/// Leases the oldest unfinished crawl job that is not leased by another
+/// crawler instance.
+pub fn lease_crawl_job(
+    conn: &mut PgConnection,
+    owner: &str,
+    lease: Duration,
+    exclude_job_ids: &[i32],
+) -> Result<Option<models::CrawlJob>> {
+    use diesel::sql_types::{Array, Double, Integer, Text};
+
+    Ok(diesel::sql_query(include_str!("lease_crawl_job.sql"))
+        .bind::<Text, _>(owner)
+        .bind::<Double, _>(lease.as_secs_f64())
+        .bind::<Array<Integer>, _>(exclude_job_ids)
+        .get_result(conn)
+        .optional()?)
+}
+
+pub fn renew_crawl_job_lease(
+    conn: &mut PgConnection,
+    job_id: i32,
+    owner: &str,
+    lease: Duration,
+) -> Result<()> {
+    use diesel::sql_types::{Double, Integer, Text};
+
+    let affected = diesel::sql_query(
+        "UPDATE crawl_job SET lease_expires = now() + make_interval(secs => $3)
+         WHERE crawl_job_id = $1 AND lease_owner = $2",
+    )
+    .bind::<Integer, _>(job_id)
+    .bind::<Text, _>(owner)
+    .bind::<Double, _>(lease.as_secs_f64())
+    .execute(conn)?;
+    if affected == 0 {
+        warn!("Lost lease on crawl job {job_id}");
+    }
+    Ok(())
+}
+
+/// Gives up the lease on a crawl job and marks it finished if requested.
+pub fn release_crawl_job(conn: &mut PgConnection, job_id: i32, finished: bool) -> Result<()> {
+    use diesel::sql_types::{Bool, Integer};
+
+    diesel::sql_query(
+        "UPDATE crawl_job SET
+           lease_owner = NULL,
+           lease_expires = NULL,
+           finished = CASE WHEN $2 THEN now() END
+         WHERE crawl_job_id = $1",
+    )
+    .bind::<Integer, _>(job_id)
+    .bind::<Bool, _>(finished)
+    .execute(conn)?;
+    Ok(())
+}
+
+/// A feed to subscribe to
+pub struct NewFeed<'a> {
+    pub url: &'a Url,
+    pub title: Option<&'a str>,
+    /// Path of nested categories, e.g. `Tech/Rust`
+    pub category: Option<&'a str>,
+}
+
+/// Subscribes to feeds, returns the number of new subscriptions and
+/// subscriptions moved to another category
+pub fn insert_feeds(
+    conn: &mut PgConnection,
+    feeds: &[NewFeed],
+    poll_interval: Duration,
+) -> Result<usize> {
+    use diesel::sql_types::{Array, Double, Nullable, Text};
+
+    let urls: Vec<&str> = feeds.iter().map(|f| f.url.as_str()).collect();
+    let titles: Vec<Option<&str>> = feeds.iter().map(|f| f.title).collect();
+    let categories: Vec<Option<&str>> = feeds.iter().map(|f| f.category).collect();
+    Ok(diesel::sql_query(
+        "INSERT INTO feed (url, title, category, poll_interval)
+         SELECT url, title, category, make_interval(secs => $4)
+         FROM unnest($1, $2, $3) AS new (url, title, category)
+         ON CONFLICT (url) DO UPDATE SET
+           title = COALESCE(feed.title, EXCLUDED.title),
+           category = EXCLUDED.category
+         WHERE EXCLUDED.category IS NOT NULL
+           AND EXCLUDED.category IS DISTINCT FROM feed.category",
+    )
+    .bind::<Array<Text>, _>(&urls)
+    .bind::<Array<Nullable<Text>>, _>(&titles)
+    .bind::<Array<Nullable<Text>>, _>(&categories)
+    .bind::<Double, _>(poll_interval.as_secs_f64())
+    .execute(conn)?)
+}
+
+/// All subscriptions ordered by category and title
+pub fn select_subscriptions(conn: &mut PgConnection) -> Result<Vec<models::Subscription>> {
+    use crate::db::schema::feed::dsl::{category, feed, title, url};
+
+    Ok(feed
+        .order((category.asc().nulls_first(), title.asc(), url.asc()))
+        .select(models::Subscription::as_select())
         .load(conn)?)
 }
 
+/// Categories of the subscriptions, without their parent categories
+pub fn select_feed_categories(conn: &mut PgConnection) -> Result<Vec<String>> {
+    use crate::db::schema::feed::dsl::{category, feed};
+
+    let categories: Vec<Option<String>> = feed
+        .select(category)
+        .filter(category.is_not_null())
+        .distinct()
+        .order(category.asc())
+        .load(conn)?;
+    Ok(categories.into_iter().flatten().collect())
+}
+
+/// Newest items of the feeds in the category and its subcategories, or of
+/// all feeds
+pub fn select_feed_entries(
+    conn: &mut PgConnection,
+    in_category: Option<&str>,
+    limit: i64,
+) -> Result<Vec<models::FeedEntry>> {
+    use crate::db::schema::{feed, feed_item};
+    use diesel::dsl::sql;
+    use diesel::sql_types::Timestamptz;
+
+    let mut query = feed_item::table
+        .inner_join(feed::table)
+        .select((
+            feed_item::feed_item_id,
+            feed_item::guid,
+            feed_item::link,
+            feed_item::title,
+            feed_item::author,
+            feed_item::published,
+            feed_item::updated,
+            feed_item::content,
+            feed_item::fetched,
+            feed::url,
+            feed::title,
+        ))
+        .order(sql::<Timestamptz>("COALESCE(feed_item.published, feed_item.fetched)").desc())
+        .limit(limit)
+        .into_boxed();
+    if let Some(c) = in_category {
+        let subcategories = format!(
+            "{}/%",
+            c.replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        query = query.filter(feed::category.eq(c).or(feed::category.like(subcategories)));
+    }
+    Ok(query.load(conn)?)
+}
+
+/// Adds the links of new items to the standing crawl job of the feed, which
+/// is created on first use. The job is reopened and the hosts of the links
+/// are added to its scope. Returns the id of the job.
+pub fn insert_feed_archive_urls(
+    conn: &mut PgConnection,
+    feed: &models::Feed,
+    links: &[&Url],
+) -> Result<i32> {
+    use crate::db::schema::feed::dsl;
+    use diesel::sql_types::{Array, Integer, Text};
+
+    let mut hosts: Vec<&str> = links
+        .iter()
+        .filter_map(|l| url_util::host_port(l))
+        .collect();
+    hosts.sort_unstable();
+    hosts.dedup();
+    let urls: Vec<NewUrl> = links
+        .iter()
+        .map(|url| NewUrl {
+            url,
+            depth: 0,
+            priority: 1.0,
+        })
+        .collect();
+    conn.transaction(|conn| {
+        let job_id = if let Some(id) = feed.crawl_job_id {
+            id
+        } else {
+            let id = insert_crawl_job(conn, &[], &[], 0)?;
+            diesel::update(dsl::feed.find(feed.feed_id))
+                .set(dsl::crawl_job_id.eq(id))
+                .execute(conn)?;
+            id
+        };
+        diesel::sql_query(
+            "UPDATE crawl_job SET
+               scope = ARRAY(SELECT DISTINCT unnest(scope || $2) ORDER BY 1),
+               finished = NULL
+             WHERE crawl_job_id = $1",
+        )
+        .bind::<Integer, _>(job_id)
+        .bind::<Array<Text>, _>(&hosts)
+        .execute(conn)?;
+        insert_urls(conn, job_id, &urls)?;
+        Ok(job_id)
+    })
+}
+
+/// Feeds due to be polled, longest overdue first
+pub fn select_due_feeds(conn: &mut PgConnection) -> Result<Vec<models::Feed>> {
+    use crate::db::schema::feed::dsl::{feed, next_poll};
+    use diesel::dsl::now;
+
+    Ok(feed
+        .filter(next_poll.le(now))
+        .order(next_poll.asc())
+        .select(models::Feed::as_select())
+        .load(conn)?)
+}
+
+/// Earliest time a feed is due to be polled
+pub fn select_next_poll(conn: &mut PgConnection) -> Result<Option<DateTime<Utc>>> {
+    use crate::db::schema::feed::dsl::{feed, next_poll};
+
+    Ok(feed.select(diesel::dsl::min(next_poll)).first(conn)?)
+}
+
+/// Stores the metadata and cache validators of a successfully fetched feed
+pub fn update_feed(
+    conn: &mut PgConnection,
+    id: i32,
+    feed_title: Option<&str>,
+    home: Option<&Url>,
+    validators: &Validators,
+) -> Result<()> {
+    use crate::db::schema::feed::dsl::{feed, home_url, http_etag, http_last_modified, title};
+
+    let last_modified: Option<DateTime<Utc>> = validators.last_modified.map(Into::into);
+    diesel::update(feed.find(id))
+        .set((
+            title.eq(feed_title),
+            home_url.eq(home.map(Url::as_str)),
+            http_etag.eq(&validators.etag),
+            http_last_modified.eq(last_modified),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Points a permanently redirected feed to its new url, unless the new url
+/// is subscribed already
+pub fn update_feed_url(conn: &mut PgConnection, id: i32, to: &Url) -> Result<()> {
+    use diesel::sql_types::{Integer, Text};
+
+    let affected = diesel::sql_query(
+        "UPDATE feed SET url = $2 WHERE feed_id = $1
+           AND NOT EXISTS (SELECT 1 FROM feed WHERE url = $2)",
+    )
+    .bind::<Integer, _>(id)
+    .bind::<Text, _>(to.as_str())
+    .execute(conn)?;
+    if affected == 0 {
+        info!("Feed {id} redirects to {to}, which is subscribed already");
+    }
+    Ok(())
+}
+
+/// Sets the next poll of a feed, by default after its poll interval
+pub fn schedule_feed(
+    conn: &mut PgConnection,
+    id: i32,
+    fetched: bool,
+    next_poll: Option<DateTime<Utc>>,
+) -> Result<()> {
+    use diesel::sql_types::{Bool, Integer, Nullable, Timestamptz};
+
+    diesel::sql_query(
+        "UPDATE feed SET
+           next_poll = COALESCE($3, now() + poll_interval),
+           last_fetched = CASE WHEN $2 THEN now() ELSE last_fetched END
+         WHERE feed_id = $1",
+    )
+    .bind::<Integer, _>(id)
+    .bind::<Bool, _>(fetched)
+    .bind::<Nullable<Timestamptz>, _>(next_poll)
+    .execute(conn)?;
+    Ok(())
+}
+
+#[derive(QueryableByName)]
+struct Upserted {
+    #[diesel(sql_type = diesel::sql_types::Bool)]
+    inserted: bool,
+}
+
+/// Stores new and updated items of a feed, returns their number and the
+/// links of the new items. Items without id and link can not be told apart
+/// and are skipped.
+pub fn upsert_feed_items<'a>(
+    conn: &mut PgConnection,
+    id: i32,
+    items: &'a [FeedItem],
+) -> Result<(usize, Vec<&'a Url>)> {
+    use diesel::sql_types::{Integer, Nullable, Text, Timestamptz};
+
+    conn.transaction(|conn| {
+        let mut affected = 0;
+        let mut new_links = Vec::new();
+        for item in items {
+            let link = item.link.as_ref().map(Url::as_str);
+            let Some(guid) = item.id.as_deref().or(link) else {
+                debug!("Skipping feed item without id and link: {:?}", item.title);
+                continue;
+            };
+            let upserted: Vec<Upserted> = diesel::sql_query(include_str!("upsert_feed_item.sql"))
+                .bind::<Integer, _>(id)
+                .bind::<Text, _>(guid)
+                .bind::<Nullable<Text>, _>(link)
+                .bind::<Nullable<Text>, _>(item.title.as_deref())
+                .bind::<Nullable<Text>, _>(item.author.as_deref())
+                .bind::<Nullable<Timestamptz>, _>(item.published)
+                .bind::<Nullable<Timestamptz>, _>(item.updated)
+                .bind::<Nullable<Text>, _>(item.content.as_deref().or(item.summary.as_deref()))
+                .load(conn)?;
+            affected += upserted.len();
+            if upserted.iter().any(|u| u.inserted) {
+                new_links.extend(&item.link);
+            }
+        }
+        Ok((affected, new_links))
+    })
+}
+
 pub fn init_conn() -> Result<PgConnection> {
     Ok(PgConnection::establish(&DB_URL.get())?)
 }
@@ -1,7 +1,9 @@
+use crate::fetcher::Validators;
 use crate::url_util;
+use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 
-#[derive(Debug, Queryable, Selectable, Insertable, PartialEq)]
+#[derive(Debug, Queryable, QueryableByName, Selectable, Insertable, PartialEq)]
 #[diesel(table_name = crate::db::schema::domain)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Domain {
@@ -9,19 +11,118 @@ pub struct Domain {
     pub name: String,
 }
 
-#[derive(Debug, Queryable, Selectable, Insertable, PartialEq)]
+#[derive(Debug, Queryable, QueryableByName, Selectable, Insertable, PartialEq)]
 #[diesel(table_name = crate::db::schema::url)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[diesel(belongs_to(Domain, foreign_key = domain_id))]
+#[allow(clippy::struct_field_names)]
 pub struct Url {
     pub url_id: i32,
     pub domain_id: i32,
     pub path: String,
     pub query: Option<String>,
+    pub crawl_depth: Option<i16>,
+    pub crawl_priority: Option<f32>,
+    pub http_etag: Option<String>,
+    pub http_last_modified: Option<DateTime<Utc>>,
+}
+
+/// Row of `select_crawl_urls.sql`
+#[derive(QueryableByName)]
+pub struct CrawlUrl {
+    #[diesel(embed)]
+    pub url: Url,
+    #[diesel(embed)]
+    pub domain: Domain,
 }
 
 impl Url {
     pub fn to_url(&self, domain_name: &str) -> url::Url {
         url_util::build(domain_name, &self.path, self.query.as_deref())
     }
+
+    pub fn validators(&self) -> Validators {
+        Validators {
+            etag: self.http_etag.clone(),
+            last_modified: self.http_last_modified.map(Into::into),
+        }
+    }
+}
+
+#[derive(Debug, Queryable, Selectable, QueryableByName)]
+#[diesel(table_name = crate::db::schema::crawl_job)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[allow(clippy::struct_field_names)]
+pub struct CrawlJob {
+    pub crawl_job_id: i32,
+    pub seeds: Vec<Option<String>>,
+    pub scope: Vec<Option<String>>,
+    pub max_depth: i16,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::db::schema::fetch_log)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewFetchLog {
+    pub url_id: i32,
+    pub fetched: DateTime<Utc>,
+    pub status: Option<i16>,
+    pub duration_ms: i32,
+    pub content_type: Option<String>,
+    pub body_size: Option<i32>,
+    pub warc_filename: Option<String>,
+    pub warc_offset: Option<i64>,
+    pub error_class: Option<String>,
+    pub payload_digest: Option<String>,
+    pub warc_length: Option<i64>,
+    pub target_uri: Option<String>,
+}
+
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = crate::db::schema::feed)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[allow(clippy::struct_field_names)]
+pub struct Feed {
+    pub feed_id: i32,
+    pub url: String,
+    pub http_etag: Option<String>,
+    pub http_last_modified: Option<DateTime<Utc>>,
+    /// Standing crawl job archiving the pages of new items
+    pub crawl_job_id: Option<i32>,
+}
+
+impl Feed {
+    pub fn validators(&self) -> Validators {
+        Validators {
+            etag: self.http_etag.clone(),
+            last_modified: self.http_last_modified.map(Into::into),
+        }
+    }
+}
+
+/// A feed as listed in exports
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = crate::db::schema::feed)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Subscription {
+    pub url: String,
+    pub title: Option<String>,
+    pub home_url: Option<String>,
+    pub category: Option<String>,
+}
+
+/// A feed item with the url and title of its feed
+#[derive(Debug, Queryable)]
+pub struct FeedEntry {
+    pub feed_item_id: i64,
+    pub guid: String,
+    pub link: Option<String>,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub published: Option<DateTime<Utc>>,
+    pub updated: Option<DateTime<Utc>>,
+    pub content: Option<String>,
+    pub fetched: DateTime<Utc>,
+    pub feed_url: String,
+    pub feed_title: Option<String>,
 }
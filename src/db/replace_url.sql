@@ -0,0 +1,25 @@
+-- The crawl jobs of the redirected url $1, $2, $3 crawl its target $4
+-- instead. The row of the redirected url is kept with its fetch log.
+WITH old AS (
+   SELECT url_id, crawl_depth, crawl_priority FROM url JOIN domain USING (domain_id)
+   WHERE name = $1 AND path = $2 AND query IS NOT DISTINCT FROM $3
+   )
+, target AS (
+   UPDATE url SET
+     crawl_depth = LEAST(url.crawl_depth, old.crawl_depth),
+     crawl_priority = GREATEST(url.crawl_priority, old.crawl_priority)
+   FROM old
+   WHERE url.url_id = $4
+   )
+, moved AS (
+   INSERT INTO crawl_job_url (crawl_job_id, url_id, crawl_depth, crawl_priority)
+     SELECT crawl_job_id, $4, crawl_depth, crawl_priority
+     FROM crawl_job_url
+     WHERE url_id IN (SELECT url_id FROM old)
+   ON CONFLICT (crawl_job_id, url_id) DO UPDATE SET
+     crawl_depth = LEAST(crawl_job_url.crawl_depth, EXCLUDED.crawl_depth),
+     crawl_priority = GREATEST(crawl_job_url.crawl_priority, EXCLUDED.crawl_priority)
+   )
+DELETE FROM crawl_job_url
+WHERE url_id IN (SELECT url_id FROM old)
+;
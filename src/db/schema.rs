@@ -1,5 +1,99 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    /// Representation of the `crawl_job` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crawl_job (crawl_job_id) {
+        /// The `crawl_job_id` column of the `crawl_job` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crawl_job_id -> Int4,
+        /// The `seeds` column of the `crawl_job` table.
+        ///
+        /// Its SQL type is `Array<Nullable<Text>>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        seeds -> Array<Nullable<Text>>,
+        /// The `scope` column of the `crawl_job` table.
+        ///
+        /// Its SQL type is `Array<Nullable<Text>>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        scope -> Array<Nullable<Text>>,
+        /// The `max_depth` column of the `crawl_job` table.
+        ///
+        /// Its SQL type is `Int2`.
+        ///
+        /// (Automatically generated by Diesel.)
+        max_depth -> Int2,
+        /// The `created` column of the `crawl_job` table.
+        ///
+        /// Its SQL type is `Timestamptz`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created -> Timestamptz,
+        /// The `started` column of the `crawl_job` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamptz>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        started -> Nullable<Timestamptz>,
+        /// The `finished` column of the `crawl_job` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamptz>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        finished -> Nullable<Timestamptz>,
+        /// The `lease_owner` column of the `crawl_job` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        lease_owner -> Nullable<Text>,
+        /// The `lease_expires` column of the `crawl_job` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamptz>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        lease_expires -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    /// Representation of the `crawl_job_url` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crawl_job_url (crawl_job_id, url_id) {
+        /// The `crawl_job_id` column of the `crawl_job_url` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crawl_job_id -> Int4,
+        /// The `url_id` column of the `crawl_job_url` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        url_id -> Int4,
+        /// The `crawl_depth` column of the `crawl_job_url` table.
+        ///
+        /// Its SQL type is `Int2`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crawl_depth -> Int2,
+        /// The `crawl_priority` column of the `crawl_job_url` table.
+        ///
+        /// Its SQL type is `Float4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crawl_priority -> Float4,
+    }
+}
+
 diesel::table! {
     /// Representation of the `domain` table.
     ///
@@ -20,6 +114,278 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Representation of the `domain_feed` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    domain_feed (domain_id, url) {
+        /// The `domain_id` column of the `domain_feed` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        domain_id -> Int4,
+        /// The `url` column of the `domain_feed` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        url -> Text,
+        /// The `discovered` column of the `domain_feed` table.
+        ///
+        /// Its SQL type is `Timestamptz`.
+        ///
+        /// (Automatically generated by Diesel.)
+        discovered -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    /// Representation of the `feed` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    feed (feed_id) {
+        /// The `feed_id` column of the `feed` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        feed_id -> Int4,
+        /// The `url` column of the `feed` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        url -> Text,
+        /// The `title` column of the `feed` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        title -> Nullable<Text>,
+        /// The `home_url` column of the `feed` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        home_url -> Nullable<Text>,
+        /// The `poll_interval` column of the `feed` table.
+        ///
+        /// Its SQL type is `Interval`.
+        ///
+        /// (Automatically generated by Diesel.)
+        poll_interval -> Interval,
+        /// The `next_poll` column of the `feed` table.
+        ///
+        /// Its SQL type is `Timestamptz`.
+        ///
+        /// (Automatically generated by Diesel.)
+        next_poll -> Timestamptz,
+        /// The `last_fetched` column of the `feed` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamptz>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_fetched -> Nullable<Timestamptz>,
+        /// The `http_etag` column of the `feed` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        http_etag -> Nullable<Text>,
+        /// The `http_last_modified` column of the `feed` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamptz>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        http_last_modified -> Nullable<Timestamptz>,
+        /// The `created` column of the `feed` table.
+        ///
+        /// Its SQL type is `Timestamptz`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created -> Timestamptz,
+        /// The `crawl_job_id` column of the `feed` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crawl_job_id -> Nullable<Int4>,
+        /// The `category` column of the `feed` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        category -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    /// Representation of the `feed_item` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    feed_item (feed_item_id) {
+        /// The `feed_item_id` column of the `feed_item` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        feed_item_id -> Int8,
+        /// The `feed_id` column of the `feed_item` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        feed_id -> Int4,
+        /// The `guid` column of the `feed_item` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        guid -> Text,
+        /// The `link` column of the `feed_item` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        link -> Nullable<Text>,
+        /// The `title` column of the `feed_item` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        title -> Nullable<Text>,
+        /// The `author` column of the `feed_item` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        author -> Nullable<Text>,
+        /// The `published` column of the `feed_item` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamptz>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        published -> Nullable<Timestamptz>,
+        /// The `updated` column of the `feed_item` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamptz>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated -> Nullable<Timestamptz>,
+        /// The `content` column of the `feed_item` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        content -> Nullable<Text>,
+        /// The `fetched` column of the `feed_item` table.
+        ///
+        /// Its SQL type is `Timestamptz`.
+        ///
+        /// (Automatically generated by Diesel.)
+        fetched -> Timestamptz,
+        /// The `read` column of the `feed_item` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        read -> Bool,
+        /// The `starred` column of the `feed_item` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        starred -> Bool,
+    }
+}
+
+diesel::table! {
+    /// Representation of the `fetch_log` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    fetch_log (fetch_log_id) {
+        /// The `fetch_log_id` column of the `fetch_log` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        fetch_log_id -> Int8,
+        /// The `url_id` column of the `fetch_log` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        url_id -> Nullable<Int4>,
+        /// The `fetched` column of the `fetch_log` table.
+        ///
+        /// Its SQL type is `Timestamptz`.
+        ///
+        /// (Automatically generated by Diesel.)
+        fetched -> Timestamptz,
+        /// The `status` column of the `fetch_log` table.
+        ///
+        /// Its SQL type is `Nullable<Int2>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        status -> Nullable<Int2>,
+        /// The `duration_ms` column of the `fetch_log` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        duration_ms -> Int4,
+        /// The `content_type` column of the `fetch_log` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        content_type -> Nullable<Text>,
+        /// The `body_size` column of the `fetch_log` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        body_size -> Nullable<Int4>,
+        /// The `warc_filename` column of the `fetch_log` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        warc_filename -> Nullable<Text>,
+        /// The `warc_offset` column of the `fetch_log` table.
+        ///
+        /// Its SQL type is `Nullable<Int8>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        warc_offset -> Nullable<Int8>,
+        /// The `error_class` column of the `fetch_log` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        error_class -> Nullable<Text>,
+        /// The `payload_digest` column of the `fetch_log` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        payload_digest -> Nullable<Text>,
+        /// The `warc_length` column of the `fetch_log` table.
+        ///
+        /// Its SQL type is `Nullable<Int8>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        warc_length -> Nullable<Int8>,
+        /// The `target_uri` column of the `fetch_log` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        target_uri -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     /// Representation of the `url` table.
     ///
@@ -73,9 +439,36 @@ diesel::table! {
         ///
         /// (Automatically generated by Diesel.)
         http_last_modified -> Nullable<Timestamptz>,
+        /// The `not_before` column of the `url` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamptz>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        not_before -> Nullable<Timestamptz>,
+        /// The `revisit_interval` column of the `url` table.
+        ///
+        /// Its SQL type is `Interval`.
+        ///
+        /// (Automatically generated by Diesel.)
+        revisit_interval -> Interval,
     }
 }
 
+diesel::joinable!(crawl_job_url -> crawl_job (crawl_job_id));
+diesel::joinable!(crawl_job_url -> url (url_id));
+diesel::joinable!(domain_feed -> domain (domain_id));
+diesel::joinable!(feed -> crawl_job (crawl_job_id));
+diesel::joinable!(feed_item -> feed (feed_id));
+diesel::joinable!(fetch_log -> url (url_id));
 diesel::joinable!(url -> domain (domain_id));
 
-diesel::allow_tables_to_appear_in_same_query!(domain, url,);
+diesel::allow_tables_to_appear_in_same_query!(
+    crawl_job,
+    crawl_job_url,
+    domain,
+    domain_feed,
+    feed,
+    feed_item,
+    fetch_log,
+    url,
+);
@@ -0,0 +1,26 @@
+-- The urls with the highest priority of each domain of a crawl job
+SELECT url_id, domain_id, path, query, crawl_depth, crawl_priority,
+  http_etag, http_last_modified, name
+FROM (
+   SELECT url.url_id, url.domain_id, url.path, url.query,
+     j.crawl_depth, j.crawl_priority, url.http_etag, url.http_last_modified,
+     domain.name,
+     row_number() OVER (PARTITION BY url.domain_id ORDER BY j.crawl_priority DESC) AS n
+   FROM crawl_job_url j
+   JOIN url USING (url_id)
+   JOIN domain USING (domain_id)
+   WHERE j.crawl_job_id = $1
+     AND j.crawl_depth <= $2
+     AND j.crawl_priority > 0
+     AND url.url_id <> ALL ($3)
+     AND domain.name <> ALL ($5)
+     AND (url.not_before IS NULL OR url.not_before <= now())
+     AND NOT EXISTS (
+        SELECT 1 FROM fetch_log f
+        WHERE f.url_id = url.url_id AND f.fetched > now() - url.revisit_interval
+          -- failed fetches are retried, deferred by the crawler
+          AND f.error_class IS NULL
+        )
+   ) ranked
+WHERE n <= $4
+;
@@ -0,0 +1,13 @@
+-- The row of the url, inserted if unknown
+WITH ins AS (
+   INSERT INTO url (domain_id, path, query)
+   SELECT domain_id, $2, $3 FROM domain WHERE name = $1
+   ON CONFLICT (domain_id, path, query) DO NOTHING
+   RETURNING url_id
+   )
+SELECT url_id AS id FROM ins
+UNION ALL
+SELECT url_id FROM url JOIN domain USING (domain_id)
+WHERE name = $1 AND path = $2 AND query IS NOT DISTINCT FROM $3
+LIMIT 1
+;
@@ -0,0 +1,17 @@
+-- An item is known by its guid. Items with a known link but another guid
+-- are skipped, some feeds change the guid of unchanged items.
+INSERT INTO feed_item (feed_id, guid, link, title, author, published, updated, content)
+  SELECT $1, $2, $3, $4, $5, $6, $7, $8
+  WHERE NOT EXISTS (
+    SELECT 1 FROM feed_item WHERE feed_id = $1 AND link = $3 AND guid <> $2
+  )
+-- an item updated since the last poll
+ON CONFLICT (feed_id, guid) DO UPDATE SET
+  link = EXCLUDED.link,
+  title = EXCLUDED.title,
+  author = EXCLUDED.author,
+  updated = EXCLUDED.updated,
+  content = EXCLUDED.content
+WHERE EXCLUDED.updated > feed_item.updated
+RETURNING (xmax = 0) AS inserted
+;
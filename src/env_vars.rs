@@ -1,5 +1,12 @@
+//! Required variables come first, optional ones after a semicolon:
+//!
+//!     env_vars![ARCHIVE_DIR BOT_NAME; CRAWL_WORKERS];
+//!
+//! Optional variables are read with a default value, e.g.
+//! `CRAWL_WORKERS.parse_or(8)`.
+//!
 //! TODO
-//! It would be nice to also declare optional types and default values:
+//! It would be nice to also declare types and default values:
 //!
 //!     env_vars!(
 //!       ARCHIVE_DIR std::path::PatBuf,
@@ -29,7 +36,7 @@ macro_rules! count_tts {
 }
 
 macro_rules! evwc {
-    ( $cnt:expr; $($name:ident)+ ) => {
+    ( $cnt:expr; $($name:ident)+ ; $($opt_name:ident)* ) => {
         mod env_config {
             #[derive(Debug)]
             pub struct EnvConfig<'a> {
@@ -63,6 +70,18 @@ macro_rules! evwc {
                         Ok(v) => v
                     }
                 }
+
+                /// Parses the variable or returns the default if it is not set
+                pub fn parse_or<T>(&self, default: T) -> T
+                where
+                    T: std::str::FromStr,
+                    T::Err: std::fmt::Display,
+                {
+                    if std::env::var_os(self.name).is_none() {
+                        return default;
+                    }
+                    self.parse()
+                }
             }
 
             $(pub const $name: EnvConfig =
@@ -70,6 +89,11 @@ macro_rules! evwc {
                   name: stringify!($name)
               }; ) +
 
+            $(pub const $opt_name: EnvConfig =
+              EnvConfig{
+                  name: stringify!($opt_name)
+              }; ) *
+
             pub fn all_vars() -> [EnvConfig<'static>; $cnt] {
                 [$($name,) +]
             }
@@ -96,6 +120,9 @@ macro_rules! evwc {
                 for var in all_vars() {
                     map.insert(var.name.to_string(), var.get());
                 }
+                $(if let Ok(v) = std::env::var($opt_name.name) {
+                    map.insert($opt_name.name.to_string(), v);
+                }) *
                 map
             }
         }
@@ -103,5 +130,7 @@ macro_rules! evwc {
 }
 
 macro_rules! env_vars {
-  ( $($env_var:tt)+ ) => (evwc!(count_tts!($($env_var)+); $($env_var)+ );)
+  ( $($env_var:ident)+ $(; $($opt_var:ident)+)? ) => (
+      evwc!(count_tts!($($env_var)+); $($env_var)+ ; $($($opt_var)+)? );
+  )
 }
@@ -0,0 +1,225 @@
+//! Feed reader: polls subscribed feeds and stores their items.
+//!
+//! Each feed is polled on its own schedule, every `poll_interval`, or later
+//! if the server asks for it with `Retry-After`. Feeds are fetched through
+//! the [`Fetcher`], so politeness, robots.txt and WARC archiving apply like
+//! for crawled urls. Items are deduplicated across polls by their id, or
+//! their link if they have no id. The pages of new items are archived by a
+//! standing crawl job of each feed.
+
+use crate::db::{self, models, NewFeed};
+use crate::env_config::BOT_NAME;
+use crate::fetcher::{FetchResult, Fetcher, Validators};
+use crate::link_extractor::feed;
+use crate::politeness::PolitenessMap;
+use crate::robotstxt::{CheckResult, RobotsTxt};
+use crate::signal_handler::{Grace, SignalHandler};
+use crate::warc;
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use diesel::PgConnection;
+use http::StatusCode;
+use std::sync::atomic::AtomicU32;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use url::Url;
+
+pub mod opml;
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_hours(1);
+/// Maximum number of redirect hops followed from a feed url
+const MAX_REDIRECTS: usize = 5;
+/// Longest sleep between checks for due feeds, new subscriptions are
+/// picked up after this
+const MAX_IDLE: Duration = Duration::from_mins(1);
+
+/// Command line: `subscribe [--interval MINUTES] FEED_URL...`
+pub fn subscribe(args: &[String]) -> Result<()> {
+    let mut urls: Vec<Url> = Vec::new();
+    let mut interval = DEFAULT_POLL_INTERVAL;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--interval" => {
+                let value = args.next().ok_or(anyhow!("--interval needs a value"))?;
+                interval = Duration::from_mins(value.parse()?);
+            }
+            url => urls.push(Url::parse(url)?),
+        }
+    }
+    if urls.is_empty() {
+        bail!("Usage: subscribe [--interval MINUTES] FEED_URL...");
+    }
+    // A feed can only be upserted once per statement
+    urls.sort();
+    urls.dedup();
+    let feeds: Vec<NewFeed> = urls
+        .iter()
+        .map(|url| NewFeed {
+            url,
+            title: None,
+            category: None,
+        })
+        .collect();
+    let added = db::insert_feeds(&mut db::init_conn()?, &feeds, interval)?;
+    info!("Subscribed to {added} new feeds");
+    Ok(())
+}
+
+/// Command line: `update-feeds`, polls feeds until interrupted
+pub fn update(signal_handler: &SignalHandler) -> Result<()> {
+    let bot_name = BOT_NAME.get();
+    let politeness = PolitenessMap::new_shared();
+    let archive_file_cnt = Arc::new(AtomicU32::new(warc::next_file_serial()?));
+    let mut updater = Updater {
+        conn: db::init_conn()?,
+        fetcher: Fetcher::new(&bot_name, politeness, archive_file_cnt)?,
+        robotstxt: RobotsTxt::new(&bot_name),
+    };
+    updater.run(&signal_handler.grace())
+}
+
+struct Updater {
+    conn: PgConnection,
+    fetcher: Fetcher,
+    robotstxt: RobotsTxt,
+}
+
+impl Updater {
+    fn run(&mut self, grace: &Grace) -> Result<()> {
+        while !grace.is_interrupted() {
+            let due = db::select_due_feeds(&mut self.conn)?;
+            for f in &due {
+                if grace.is_interrupted() {
+                    break;
+                }
+                // A failed poll, e.g. a redirect to the url of another
+                // subscription, must not stop the polling of the others
+                if let Err(e) = self.poll(f) {
+                    warn!("Polling feed {} failed: {e:#}", f.url);
+                    db::schedule_feed(&mut self.conn, f.feed_id, false, None)?;
+                }
+            }
+            if due.is_empty() {
+                let next_poll = db::select_next_poll(&mut self.conn)?;
+                let wait = next_poll
+                    .and_then(|t| SystemTime::from(t).duration_since(SystemTime::now()).ok())
+                    .map_or(MAX_IDLE, |wait| wait.min(MAX_IDLE));
+                debug!("No feed due, waiting {wait:?}");
+                let until = SystemTime::now() + wait;
+                while SystemTime::now() < until && !grace.is_interrupted() {
+                    thread::sleep(Duration::from_secs(1));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn poll(&mut self, f: &models::Feed) -> Result<()> {
+        let id = f.feed_id;
+        let url = Url::parse(&f.url)?;
+        let fr = match self.fetch(id, &url, &f.validators()) {
+            Ok(Some(fr)) => fr,
+            Ok(None) => return Ok(()),
+            Err(e) => {
+                info!("Polling feed {url} failed: {e}");
+                return db::schedule_feed(&mut self.conn, id, false, None);
+            }
+        };
+        if fr.is_throttled() {
+            info!("Got status {} for feed {url}, polling later", fr.status);
+            let retry_at = fr.retry_after.map(DateTime::<Utc>::from);
+            return db::schedule_feed(&mut self.conn, id, false, retry_at);
+        }
+        if fr.status == StatusCode::OK {
+            match feed::parse(&fr.body_str(), &url) {
+                Ok(parsed) => {
+                    db::update_feed(
+                        &mut self.conn,
+                        id,
+                        parsed.title.as_deref(),
+                        parsed.home.as_ref(),
+                        &fr.validators,
+                    )?;
+                    let (stored, new_links) =
+                        db::upsert_feed_items(&mut self.conn, id, &parsed.items)?;
+                    info!("Feed {url}: {stored} new or updated items");
+                    self.archive_items(f, &new_links)?;
+                }
+                Err(e) => warn!("Could not parse feed {url}: {e}"),
+            }
+        } else if fr.is_unchanged() {
+            debug!("Feed unchanged since last poll: {url}");
+        } else {
+            info!("Got status {} for feed {url}", fr.status);
+        }
+        db::schedule_feed(&mut self.conn, id, true, None)
+    }
+
+    /// Adds the pages of new feed items to the crawl job of the feed, their
+    /// links are not followed
+    fn archive_items(&mut self, f: &models::Feed, links: &[&Url]) -> Result<()> {
+        if links.is_empty() {
+            return Ok(());
+        }
+        let job_id = db::insert_feed_archive_urls(&mut self.conn, f, links)?;
+        info!(
+            "Crawl job {job_id} archives {} new items of feed {}",
+            links.len(),
+            f.url
+        );
+        Ok(())
+    }
+
+    /// Fetches the feed and follows redirects. A chain of only permanent
+    /// redirects moves the subscription to the final target. Validators are
+    /// only sent to and kept for the url they were recorded for.
+    ///
+    /// Returns None if robots.txt does not allow fetching now, the feed has
+    /// been rescheduled then.
+    fn fetch(
+        &mut self,
+        id: i32,
+        url: &Url,
+        validators: &Validators,
+    ) -> Result<Option<FetchResult>> {
+        let mut url = url.clone();
+        let mut validators = validators;
+        let none = Validators::default();
+        let mut permanent = true;
+        for _ in 0..=MAX_REDIRECTS {
+            match self.robotstxt.check(&url, &mut self.fetcher)? {
+                CheckResult::Allowed => (),
+                CheckResult::Disallowed => {
+                    info!("Polling feed {url} forbidden by robots.txt");
+                    db::schedule_feed(&mut self.conn, id, false, None)?;
+                    return Ok(None);
+                }
+                CheckResult::Retry(seconds) => {
+                    info!("Retry robots check for feed {url} in {seconds}s");
+                    let retry_at = SystemTime::now() + Duration::from_secs(seconds);
+                    db::schedule_feed(&mut self.conn, id, false, Some(retry_at.into()))?;
+                    return Ok(None);
+                }
+            }
+            let mut fr = self.fetcher.fetch_conditional(&url, validators)?;
+            let Some(target) = fr.redirect_target().cloned() else {
+                if !permanent {
+                    // Stored with the feed url, which still redirects
+                    fr.validators = Validators::default();
+                }
+                return Ok(Some(fr));
+            };
+            permanent &= fr.is_permanent_redirect();
+            if permanent {
+                db::update_feed_url(&mut self.conn, id, &target)?;
+            }
+            debug!("Following feed redirect {url} -> {target}");
+            url = target;
+            validators = &none;
+        }
+        bail!("More than {MAX_REDIRECTS} redirects")
+    }
+}
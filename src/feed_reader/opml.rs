@@ -0,0 +1,422 @@
+//! Import and export of feed subscriptions as [OPML
+//! 2.0](https://opml.org/spec2.opml).
+//!
+//! Outlines without `xmlUrl` are categories. Nested categories are stored as
+//! their path, e.g. `Tech/Rust`. A `/` in the name of a category is escaped
+//! with a backslash, e.g. `Music/AC\/DC`.
+
+use super::DEFAULT_POLL_INTERVAL;
+use crate::db::{self, models, NewFeed};
+use crate::url_util::{host_port, is_http_s, with_path_only};
+use anyhow::{anyhow, bail, Result};
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::reader::Reader;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::fs;
+use std::time::SystemTime;
+use url::Url;
+
+/// Separates nested categories
+const CATEGORY_SEPARATOR: char = '/';
+/// Escapes a separator or itself in a category name
+const CATEGORY_ESCAPE: char = '\\';
+
+/// A feed outline
+#[derive(Debug, PartialEq)]
+struct Outline {
+    title: Option<String>,
+    xml_url: Url,
+    html_url: Option<Url>,
+    category: Option<String>,
+}
+
+impl Outline {
+    /// The `htmlUrl` or the domain root of the feed
+    fn homepage(&self) -> Url {
+        self.html_url
+            .clone()
+            .unwrap_or_else(|| with_path_only(&self.xml_url, ""))
+    }
+}
+
+/// Command line: `import-opml [--max-depth N] FILE`
+///
+/// Subscribes to the feeds and adds a crawl job seeded with the homepages of
+/// the feeds. By default only the homepages are crawled.
+pub fn import(args: &[String]) -> Result<()> {
+    let mut file = None;
+    let mut max_depth = 0;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--max-depth" => {
+                let value = args.next().ok_or(anyhow!("--max-depth needs a value"))?;
+                max_depth = value.parse()?;
+            }
+            path => file = Some(path),
+        }
+    }
+    let Some(file) = file else {
+        bail!("Usage: import-opml [--max-depth N] FILE");
+    };
+    let outlines = dedup(parse(&fs::read_to_string(file)?)?);
+    if outlines.is_empty() {
+        bail!("No feeds found in {file}");
+    }
+
+    let feeds: Vec<NewFeed> = outlines
+        .iter()
+        .map(|o| NewFeed {
+            url: &o.xml_url,
+            title: o.title.as_deref(),
+            category: o.category.as_deref(),
+        })
+        .collect();
+    let conn = &mut db::init_conn()?;
+    let added = db::insert_feeds(conn, &feeds, DEFAULT_POLL_INTERVAL)?;
+    info!(
+        "Imported {} feeds, {added} new or moved to another category",
+        outlines.len()
+    );
+
+    let mut seeds: Vec<String> = outlines.iter().map(|o| o.homepage().to_string()).collect();
+    seeds.sort();
+    seeds.dedup();
+    let mut scope: Vec<String> = outlines
+        .iter()
+        .filter_map(|o| host_port(&o.homepage()).map(String::from))
+        .collect();
+    scope.sort();
+    scope.dedup();
+    let id = db::insert_crawl_job(conn, &seeds, &scope, max_depth)?;
+    info!("Added crawl job {id} for {} homepages", seeds.len());
+    Ok(())
+}
+
+/// Command line: `export-opml [FILE]`, writes to stdout without `FILE`
+pub fn export(args: &[String]) -> Result<()> {
+    let subscriptions = db::select_subscriptions(&mut db::init_conn()?)?;
+    let opml = opml(&subscriptions, SystemTime::now());
+    match args.first() {
+        Some(file) => {
+            fs::write(file, opml)?;
+            info!("Exported {} feeds to {file}", subscriptions.len());
+        }
+        None => print!("{opml}"),
+    }
+    Ok(())
+}
+
+/// Feed outlines of an OPML document with the path of their categories
+fn parse(opml: &str) -> Result<Vec<Outline>> {
+    let mut reader = Reader::from_str(opml);
+    let mut outlines = Vec::new();
+    // Titles of the open outlines, None for feeds with nested outlines
+    let mut open: Vec<Option<String>> = Vec::new();
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) if e.local_name().as_ref() == b"outline" => {
+                let outline = outline(&e, &open);
+                match outline {
+                    Some(feed) => {
+                        outlines.push(feed);
+                        open.push(None);
+                    }
+                    None => open.push(attribute(&e, "text").or_else(|| attribute(&e, "title"))),
+                }
+            }
+            Event::Empty(e) if e.local_name().as_ref() == b"outline" => {
+                outlines.extend(outline(&e, &open));
+            }
+            Event::End(e) if e.local_name().as_ref() == b"outline" => {
+                open.pop();
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+    Ok(outlines)
+}
+
+/// A feed listed in several categories is imported into the first one only,
+/// a feed has only one category.
+fn dedup(outlines: Vec<Outline>) -> Vec<Outline> {
+    let mut seen = HashSet::new();
+    outlines
+        .into_iter()
+        .filter(|o| {
+            let first = seen.insert(o.xml_url.clone());
+            if !first {
+                warn!(
+                    "Skipping {} in category {}, the feed is listed before",
+                    o.xml_url,
+                    o.category.as_deref().unwrap_or("-")
+                );
+            }
+            first
+        })
+        .collect()
+}
+
+/// The feed of an outline with an http(s) `xmlUrl`
+fn outline(e: &BytesStart, open: &[Option<String>]) -> Option<Outline> {
+    let xml_url = Url::parse(&attribute(e, "xmlUrl")?)
+        .ok()
+        .filter(is_http_s)?;
+    let categories: Vec<&str> = open.iter().flatten().map(String::as_str).collect();
+    Some(Outline {
+        title: attribute(e, "title").or_else(|| attribute(e, "text")),
+        xml_url,
+        html_url: attribute(e, "htmlUrl")
+            .and_then(|u| Url::parse(&u).ok())
+            .filter(is_http_s),
+        category: (!categories.is_empty()).then(|| join_categories(&categories)),
+    })
+}
+
+/// Path of the nested categories
+fn join_categories(names: &[&str]) -> String {
+    let mut path = String::new();
+    for name in names {
+        if !path.is_empty() {
+            path.push(CATEGORY_SEPARATOR);
+        }
+        for c in name.chars() {
+            if c == CATEGORY_SEPARATOR || c == CATEGORY_ESCAPE {
+                path.push(CATEGORY_ESCAPE);
+            }
+            path.push(c);
+        }
+    }
+    path
+}
+
+/// Path and unescaped name of the category and each of its parents, parents
+/// first
+pub fn split_category(path: &str) -> Vec<(&str, String)> {
+    let mut categories = Vec::new();
+    let mut name = String::new();
+    let mut chars = path.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c == CATEGORY_SEPARATOR {
+            categories.push((&path[..i], std::mem::take(&mut name)));
+        } else if c == CATEGORY_ESCAPE {
+            name.push(chars.next().map_or(c, |(_, escaped)| escaped));
+        } else {
+            name.push(c);
+        }
+    }
+    categories.push((path, name));
+    categories
+}
+
+/// Trimmed value of the attribute, names are matched case insensitive
+fn attribute(e: &BytesStart, name: &str) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| {
+            a.key
+                .local_name()
+                .as_ref()
+                .eq_ignore_ascii_case(name.as_bytes())
+        })
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// OPML document of the subscriptions
+fn opml(subscriptions: &[models::Subscription], created: SystemTime) -> String {
+    let path = |s: &models::Subscription| -> Vec<String> {
+        s.category
+            .as_deref()
+            .map(|c| {
+                split_category(c)
+                    .into_iter()
+                    .map(|(_, name)| name)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    // Feeds of a category must be next to each other
+    let mut subscriptions: Vec<(Vec<String>, &models::Subscription)> =
+        subscriptions.iter().map(|s| (path(s), s)).collect();
+    subscriptions.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut out = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <opml version=\"2.0\">\n\
+         \x20 <head>\n\
+         \x20   <title>Lara subscriptions</title>\n\
+         \x20   <dateCreated>{}</dateCreated>\n\
+         \x20 </head>\n\
+         \x20 <body>\n",
+        httpdate::fmt_http_date(created)
+    );
+    let mut open: Vec<&str> = Vec::new();
+    for (path, s) in &subscriptions {
+        let shared = open
+            .iter()
+            .zip(path)
+            .take_while(|(a, b)| **a == b.as_str())
+            .count();
+        while open.len() > shared {
+            open.pop();
+            let _ = writeln!(out, "{}</outline>", indent(open.len()));
+        }
+        for name in &path[shared..] {
+            let _ = writeln!(
+                out,
+                "{}<outline text=\"{}\">",
+                indent(open.len()),
+                escape(name.as_str())
+            );
+            open.push(name);
+        }
+        let title = escape(s.title.as_deref().unwrap_or(&s.url)).into_owned();
+        let _ = write!(
+            out,
+            "{}<outline type=\"rss\" text=\"{title}\" title=\"{title}\" xmlUrl=\"{}\"",
+            indent(open.len()),
+            escape(&s.url)
+        );
+        if let Some(home) = &s.home_url {
+            let _ = write!(out, " htmlUrl=\"{}\"", escape(home));
+        }
+        out.push_str("/>\n");
+    }
+    while open.pop().is_some() {
+        let _ = writeln!(out, "{}</outline>", indent(open.len()));
+    }
+    out.push_str("  </body>\n</opml>\n");
+    out
+}
+
+fn indent(level: usize) -> String {
+    " ".repeat(4 + level * 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dedup, opml, parse, split_category};
+    use crate::db::models::Subscription;
+    use std::time::SystemTime;
+
+    const OPML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<opml version="2.0">
+  <head>
+    <title>Lara subscriptions</title>
+    <dateCreated>Thu, 01 Jan 1970 00:00:00 GMT</dateCreated>
+  </head>
+  <body>
+    <outline type="rss" text="Top" title="Top" xmlUrl="https://top.example/feed"/>
+    <outline text="Tech">
+      <outline type="rss" text="A &amp; B" title="A &amp; B" xmlUrl="https://ab.example/atom"/>
+      <outline text="AC/DC \m/">
+        <outline type="rss" text="Fans" title="Fans" xmlUrl="https://acdc.example/feed"/>
+      </outline>
+      <outline text="Rust">
+        <outline type="rss" text="This Week in Rust" title="This Week in Rust" xmlUrl="https://this-week-in-rust.org/rss.xml" htmlUrl="https://this-week-in-rust.org/"/>
+      </outline>
+    </outline>
+  </body>
+</opml>
+"#;
+
+    fn subscription(
+        url: &str,
+        title: &str,
+        home: Option<&str>,
+        category: Option<&str>,
+    ) -> Subscription {
+        Subscription {
+            url: url.to_string(),
+            title: Some(title.to_string()),
+            home_url: home.map(String::from),
+            category: category.map(String::from),
+        }
+    }
+
+    #[test]
+    fn import_nested_categories() {
+        let outlines = parse(OPML).unwrap();
+        let categories: Vec<Option<&str>> =
+            outlines.iter().map(|o| o.category.as_deref()).collect();
+        assert_eq!(
+            categories,
+            [
+                None,
+                Some("Tech"),
+                Some(r"Tech/AC\/DC \\m\/"),
+                Some("Tech/Rust")
+            ]
+        );
+        assert_eq!(outlines[1].title.as_deref(), Some("A & B"));
+        assert_eq!(outlines[1].homepage().as_str(), "https://ab.example/");
+        assert_eq!(
+            outlines[3].homepage().as_str(),
+            "https://this-week-in-rust.org/"
+        );
+    }
+
+    #[test]
+    fn import_duplicate_feed() {
+        let outlines = dedup(
+            parse(
+                r#"<opml version="2.0"><body>
+  <outline text="News"><outline text="Feed" xmlUrl="https://example.org/feed"/></outline>
+  <outline text="Tech"><outline text="Feed" xmlUrl="https://example.org/feed"/></outline>
+  <outline text="Other" xmlUrl="https://example.org/other"/>
+</body></opml>"#,
+            )
+            .unwrap(),
+        );
+        let feeds: Vec<(&str, Option<&str>)> = outlines
+            .iter()
+            .map(|o| (o.xml_url.as_str(), o.category.as_deref()))
+            .collect();
+        assert_eq!(
+            feeds,
+            [
+                ("https://example.org/feed", Some("News")),
+                ("https://example.org/other", None)
+            ]
+        );
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let subscriptions = [
+            subscription("https://top.example/feed", "Top", None, None),
+            subscription(
+                "https://this-week-in-rust.org/rss.xml",
+                "This Week in Rust",
+                Some("https://this-week-in-rust.org/"),
+                Some("Tech/Rust"),
+            ),
+            subscription(
+                "https://acdc.example/feed",
+                "Fans",
+                None,
+                Some(r"Tech/AC\/DC \\m\/"),
+            ),
+            subscription("https://ab.example/atom", "A & B", None, Some("Tech")),
+        ];
+        assert_eq!(opml(&subscriptions, SystemTime::UNIX_EPOCH), OPML);
+    }
+
+    #[test]
+    fn category_with_separator() {
+        let categories: Vec<(&str, String)> = split_category(r"Tech/AC\/DC \\m\/");
+        assert_eq!(
+            categories,
+            [
+                ("Tech", String::from("Tech")),
+                (r"Tech/AC\/DC \\m\/", String::from(r"AC/DC \m/"))
+            ]
+        );
+    }
+}
@@ -1,80 +1,51 @@
 // There's a std: [Fetch](https://fetch.spec.whatwg.org). However it doesn't
 // seem to apply to a crawler.
 
-use crate::env_config::{ARCHIVE_DIR, FROM};
+use crate::clock;
+use crate::db::{self, models};
+use crate::env_config::FROM;
+use crate::politeness::{SharedPoliteness, IN_FLIGHT_POLL, MAX_BACKOFF};
+use crate::warc::{self, Record, WarcLocation, WarcWriter};
 use anyhow::Result;
-use chrono::prelude::*;
-use flate2::{write::GzEncoder, Compression};
-use http::{HeaderMap, StatusCode, Version};
-use simple_moving_average::{NoSumSMA, SMA};
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{self, Write};
-use std::ops::Add;
-use std::path::PathBuf;
+use diesel::pg::PgConnection;
+use http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode, Version};
+use std::sync::atomic::AtomicU32;
 use std::sync::Arc;
+use std::thread;
 use std::time::{Duration, Instant, SystemTime};
-use ureq::config::AutoHeaderValue as AHV;
 use ureq::tls::{TlsConfig, TlsProvider};
 use ureq::Agent;
 use url::Url;
-use uuid::Uuid;
 
 // TODO Move to configuration
 /// Maximum size for HTTP response body
 const MAX_BODY_SIZE: u64 = 50 * 1024 * 1024; // 50 MB
-const MIN_FETCH_DURATION: Duration = Duration::from_millis(150); // We actually wait 3xavg fetch duration between fetches
-
-struct Politeness {
-    until: SystemTime,
-    duration_avg: NoSumSMA<Duration, u32, 10>,
-}
-
-impl Default for Politeness {
-    fn default() -> Self {
-        Politeness {
-            until: SystemTime::UNIX_EPOCH,
-            duration_avg: NoSumSMA::<Duration, u32, 10>::from_zero(Duration::ZERO),
-        }
-    }
-}
-
-impl Politeness {
-    pub fn update(&mut self, fr: &FetchResult) {
-        match fr.status.as_u16() {
-            200 => {
-                self.duration_avg
-                    .add_sample(std::cmp::max(fr.duration, MIN_FETCH_DURATION));
-                self.until = fr.start.add(self.duration_avg.get_average() * 3);
-            }
-            429 => todo!(),
-            _ => (), // todo
-        }
-    }
-
-    pub fn wait(&self) {
-        crate::clock::wait(self.until);
-    }
-}
 
 pub struct Fetcher {
-    archive_dir: PathBuf,
-    archive_file: Option<GzEncoder<File>>,
-    archive_file_cnt: u32,
-    archive_file_bytes_written: usize,
+    warc: WarcWriter,
+    /// Fetch log and lookup of archived payloads for deduplication
+    conn: PgConnection,
     agent: Agent,
-    /// FROM header for requests
-    from: String,
-    politeness: HashMap<String, Politeness>,
+    /// Headers sent with every request, see [`Self::request_headers`]
+    headers: HeaderMap,
+    politeness: SharedPoliteness,
 }
 
-impl Drop for Fetcher {
-    fn drop(&mut self) {
-        debug!("dropping fetcher");
-        if self.archive_file.is_some() {
-            if let Err(e) = self.close_archive_file() {
-                error!("{e:?}");
-            }
+/// Cache validators of a previous response used for conditional requests
+/// <https://httpwg.org/specs/rfc9110.html#validators>
+#[derive(Default, Clone)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<SystemTime>,
+}
+
+impl Validators {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let get = |name| headers.get(name).and_then(|v| v.to_str().ok());
+        Validators {
+            etag: get(header::ETAG).map(String::from),
+            last_modified: get(header::LAST_MODIFIED)
+                .and_then(|v| httpdate::parse_http_date(v).ok()),
         }
     }
 }
@@ -85,6 +56,17 @@ pub struct FetchResult {
     pub start: SystemTime,
     pub status: StatusCode,
     pub http_version: Version,
+    /// `Location` header resolved against the fetched URL
+    pub location: Option<Url>,
+    /// `Retry-After` header of 429 and 503 responses
+    pub retry_after: Option<SystemTime>,
+    /// Cache validators sent by the server
+    pub validators: Validators,
+    pub content_type: Option<String>,
+    /// Where the response has been archived
+    pub warc_location: Option<WarcLocation>,
+    /// `WARC-Payload-Digest` of the body, None for 304 responses
+    pub payload_digest: Option<String>,
 }
 
 impl FetchResult {
@@ -92,22 +74,53 @@ impl FetchResult {
         String::from_utf8_lossy(&self.body).to_string()
     }
 
+    /// Target of a redirect response
+    pub fn redirect_target(&self) -> Option<&Url> {
+        match self.status {
+            StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT => self.location.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn is_permanent_redirect(&self) -> bool {
+        matches!(
+            self.status,
+            StatusCode::MOVED_PERMANENTLY | StatusCode::PERMANENT_REDIRECT
+        )
+    }
+
+    /// Response to a conditional request: The resource did not change since
+    /// the last fetch.
+    pub fn is_unchanged(&self) -> bool {
+        self.status == StatusCode::NOT_MODIFIED
+    }
+
+    /// The server asks us to slow down, see [RFC 6585][] and
+    /// <https://developers.google.com/search/docs/crawling-indexing/reduce-crawl-rate>
+    ///
+    /// [RFC 6585]: https://www.rfc-editor.org/rfc/rfc6585#section-4
+    pub fn is_throttled(&self) -> bool {
+        matches!(
+            self.status,
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+        )
+    }
+
     fn status_line(&self) -> String {
         format!("{:?} {}\r\n", self.http_version, self.status)
     }
 }
 
 impl Fetcher {
-    pub fn new(bot_name: &str) -> Fetcher {
-        let archive_dir = ARCHIVE_DIR.parse::<PathBuf>();
-        let m = archive_dir.metadata().unwrap_or_else(|_| {
-            panic!(
-                "Could not get metadata of ARCHIVE_DIR: {}",
-                archive_dir.display()
-            )
-        });
-        assert!(m.is_dir(), "Not a dir: {}", archive_dir.display());
-
+    pub fn new(
+        bot_name: &str,
+        politeness: SharedPoliteness,
+        archive_file_cnt: Arc<AtomicU32>,
+    ) -> Result<Fetcher> {
         // TODO Get URL from a better place, e.g. Cargo.toml?
         let ua_name = format!(
             "{bot_name}/{} https://github.com/thkoch2001/lara#larabot",
@@ -115,10 +128,9 @@ impl Fetcher {
         );
         let agent = Agent::config_builder()
             .http_status_as_error(false)
-            .user_agent(AHV::Provided(Arc::new(ua_name)))
-            .max_redirects(0) // TODO: Handle redirects
-            .timeout_connect(Some(Duration::from_millis(5000)))
-            .timeout_global(Some(Duration::from_millis(20000)))
+            .max_redirects(0) // Redirects are followed by the crawler hop by hop
+            .timeout_connect(Some(Duration::from_secs(5)))
+            .timeout_global(Some(Duration::from_secs(20)))
             .tls_config(
                 TlsConfig::builder()
                     .provider(TlsProvider::NativeTls)
@@ -126,43 +138,142 @@ impl Fetcher {
             )
             .build()
             .into();
-        Fetcher {
-            archive_dir,
-            archive_file: None,
-            archive_file_cnt: 0, // TODO make this an option and search for last file of current job in case of NONE
-            archive_file_bytes_written: 0,
+        let from = FROM.get();
+        let mut headers = HeaderMap::new();
+        headers.insert(header::USER_AGENT, HeaderValue::from_str(&ua_name).unwrap());
+        headers.insert(header::FROM, HeaderValue::from_str(&from).unwrap());
+        headers.insert(header::ACCEPT, HeaderValue::from_static("*/*"));
+        headers.insert(
+            header::ACCEPT_ENCODING,
+            HeaderValue::from_static("gzip, br"),
+        );
+        Ok(Fetcher {
+            warc: WarcWriter::new(archive_file_cnt, &ua_name, &from),
+            conn: db::init_conn()?,
             agent,
-            from: FROM.get(),
-            politeness: HashMap::new(),
-        }
+            headers,
+            politeness,
+        })
     }
 
-    pub fn close_archive_file(&mut self) -> Result<()> {
-        debug!("closing archive file");
-        self.archive_file.as_mut().unwrap().try_finish()?;
-        self.archive_file = None;
-        self.archive_file_bytes_written = 0;
-        Ok(())
+    /// Responses of different crawl jobs are archived in different files.
+    pub fn set_crawl_job(&mut self, crawl_job_id: i32) -> Result<()> {
+        self.warc.set_crawl_job(crawl_job_id)
     }
 
     // TODO implement option to specify size limit
     // Yandex limits robots.txt to 500 KB https://yandex.ru/support/webmaster/controlling-robot/robots-txt.html?lang=en
     // Sitemaps are limited to 50 MB
     pub fn fetch(&mut self, url: &Url) -> Result<FetchResult> {
+        self.fetch_conditional(url, &Validators::default())
+    }
+
+    /// Sends `If-None-Match` and `If-Modified-Since` headers for the given
+    /// validators. Each fetch is logged, successful or not.
+    pub fn fetch_conditional(&mut self, url: &Url, validators: &Validators) -> Result<FetchResult> {
+        let start = SystemTime::now();
+        let fr = self.fetch_archived(url, validators);
+        self.log_fetch(url, &fr, start)?;
+        fr
+    }
+
+    fn fetch_archived(&mut self, url: &Url, validators: &Validators) -> Result<FetchResult> {
+        let authority = url.authority();
+        let until = loop {
+            if let Some(until) = self.politeness.lock().unwrap().acquire(authority) {
+                break until;
+            }
+            thread::sleep(IN_FLIGHT_POLL);
+        };
+        clock::wait(until);
+        let request_headers = self.request_headers(url, validators);
+        let (mut fr, headers) = match self.request(url, &request_headers) {
+            Ok(response) => response,
+            Err(e) => {
+                self.politeness.lock().unwrap().release(authority);
+                return Err(e);
+            }
+        };
+        self.politeness.lock().unwrap().update(authority, &fr);
+        if !fr.is_unchanged() {
+            fr.payload_digest = Some(warc::digest(&fr.body));
+        }
+        fr.warc_location = Some(self.write_to_archive(url, &fr, &request_headers, &headers)?);
+        Ok(fr)
+    }
+
+    /// Writes the fetch log entry of a fetch. The start time is only used
+    /// for failed fetches.
+    fn log_fetch(&mut self, url: &Url, fr: &Result<FetchResult>, start: SystemTime) -> Result<()> {
+        let (start, duration) = match fr {
+            Ok(fr) => (fr.start, fr.duration),
+            Err(_) => (start, start.elapsed().unwrap_or_default()),
+        };
+        let mut log = models::NewFetchLog {
+            url_id: db::select_url_id(&mut self.conn, url)?,
+            fetched: start.into(),
+            status: None,
+            duration_ms: i32::try_from(duration.as_millis()).unwrap_or(i32::MAX),
+            content_type: None,
+            body_size: None,
+            warc_filename: None,
+            warc_offset: None,
+            error_class: None,
+            payload_digest: None,
+            warc_length: None,
+            target_uri: Some(url.to_string()),
+        };
+        match fr {
+            Ok(fr) => {
+                log.status = i16::try_from(fr.status.as_u16()).ok();
+                log.content_type.clone_from(&fr.content_type);
+                log.body_size = i32::try_from(fr.body.len()).ok();
+                log.payload_digest.clone_from(&fr.payload_digest);
+                if let Some(l) = &fr.warc_location {
+                    log.warc_filename = Some(l.filename.clone());
+                    log.warc_offset = i64::try_from(l.offset).ok();
+                    log.warc_length = i64::try_from(l.length).ok();
+                }
+            }
+            Err(e) => log.error_class = Some(error_class(e).to_string()),
+        }
+        db::insert_fetch_log(&mut self.conn, &log)
+    }
+
+    /// All headers of a request. They are set explicitly instead of ureq's
+    /// defaults to archive exactly what has been sent.
+    fn request_headers(&self, url: &Url, validators: &Validators) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Ok(host) = HeaderValue::from_str(url.authority()) {
+            headers.insert(header::HOST, host);
+        }
+        headers.extend(self.headers.clone());
+        if let Some(etag) = validators
+            .etag
+            .as_ref()
+            .and_then(|v| HeaderValue::from_str(v).ok())
+        {
+            headers.insert(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = validators.last_modified {
+            let value = httpdate::fmt_http_date(last_modified);
+            headers.insert(
+                header::IF_MODIFIED_SINCE,
+                HeaderValue::from_str(&value).unwrap(),
+            );
+        }
+        headers
+    }
+
+    fn request(&self, url: &Url, request_headers: &HeaderMap) -> Result<(FetchResult, HeaderMap)> {
         debug!("Fetching {url}");
-        // todo clean old entries from politeness HashMap
-        let politeness = self
-            .politeness
-            .entry(url.authority().to_string())
-            .or_default();
-        politeness.wait();
         let start_systemtime = SystemTime::now();
         let start_instant = Instant::now();
-        let result = self
-            .agent
-            .get(url.to_string())
-            .header("FROM", self.from.clone())
-            .call();
+        let mut request = self.agent.get(url.to_string());
+        for (name, value) in request_headers {
+            request = request.header(name, value);
+        }
+        let result = request.call();
         let duration = start_instant.elapsed();
 
         let mut response = result?;
@@ -180,6 +291,14 @@ impl Fetcher {
         let status = response.status();
         let http_version = response.version();
         let headers = response.headers().clone();
+        let location = headers
+            .get(header::LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| url.join(v).ok());
+        let retry_after = headers
+            .get(header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| parse_retry_after(v, start_systemtime));
         let body = response
             .body_mut()
             .with_config()
@@ -192,81 +311,201 @@ impl Fetcher {
             start: start_systemtime,
             status,
             http_version,
+            location,
+            retry_after,
+            validators: Validators::from_headers(&headers),
+            content_type: headers
+                .get(header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(String::from),
+            warc_location: None,
+            payload_digest: None,
         };
-        politeness.update(&fr);
-        self.write_to_archive(url, &fr, &headers)?;
-        Ok(fr)
+        Ok((fr, headers))
     }
 
-    // TODO: directly compress the archive file
-    fn write_to_archive(&mut self, url: &Url, fr: &FetchResult, headers: &HeaderMap) -> Result<()> {
-        if self.archive_file.is_none() {
-            let path = format!(
-                "{}/archive_{:03}.warc.gz",
-                self.archive_dir.display(),
-                self.archive_file_cnt
-            );
-            debug!("Starting new warc file: {path}");
-            let file = File::create(path)?;
-            let gzip_encoder = GzEncoder::new(file, Compression::best());
-            self.archive_file_cnt += 1;
-            self.archive_file = Some(gzip_encoder);
-            // TODO start a new file with a warcinfo record
-        }
-        let writer = self.archive_file.as_mut().unwrap();
-        let bytes_written = Self::write_record(writer, url, fr, headers)?;
-        self.archive_file_bytes_written += bytes_written;
-
-        // TODO somehow get the size of the compressed file?
-        // file.metadata().unwrap().len() encoder has get_ref()
-        // Optimization: check metadata only after at least the threshold of uncompressed bytes has been written
-        if self.archive_file_bytes_written > 1024 * 1024 {
-            let () = self.close_archive_file()?;
-        }
-
-        Ok(())
-    }
-
-    /// WARC 1.1 spec:
-    /// <https://github.com/iipc/warc-specifications/blob/master/specifications/warc-format/warc-1.1-annotated/index.md>
-    fn write_record(
-        w: &mut GzEncoder<File>,
+    /// Writes the response followed by the request record pointing to it.
+    /// Responses are written as revisit records without payload for 304
+    /// responses and payloads archived before.
+    fn write_to_archive(
+        &mut self,
         url: &Url,
         fr: &FetchResult,
+        request_headers: &HeaderMap,
         headers: &HeaderMap,
-    ) -> io::Result<usize> {
-        let mut cnt = 0;
-        let mut headers_bytes: Vec<u8> = Vec::new();
-
-        headers_bytes.extend(fr.status_line().as_bytes());
-        for (k, v) in headers {
-            headers_bytes.extend(k.to_string().as_bytes());
-            headers_bytes.extend(b": ");
-            headers_bytes.extend(v.as_bytes());
-            headers_bytes.extend(b"\r\n");
-        }
-        headers_bytes.extend(b"\r\n");
-
-        cnt += w.write(b"WARC/1.1\r\nWARC-Type: response\r\nContent-Type: application/http; msgtype=response\r\nWARC-Record-ID: ")?;
-        cnt += w.write(Uuid::new_v4().urn().to_string().as_bytes())?;
-        cnt += w.write(b"\r\nWARC-Target-URI: ")?;
-        cnt += w.write(url.to_string().as_bytes())?;
-        cnt += w.write(b"\r\nContent-Length: ")?;
-        // TODO: check whether body.len() is correct!
-        let content_length = headers_bytes.len() + fr.body.len();
-        cnt += w.write(content_length.to_string().as_bytes())?;
-        cnt += w.write(b"\r\nWARC-Date: ")?;
-        // TODO: check correct formatting of date!
-        // WARC-Date fr.start UTC timestamp formatted according to [W3CDTF]
-        let dt: DateTime<Utc> = fr.start.into();
-        cnt += w.write(dt.to_rfc3339_opts(SecondsFormat::Secs, true).as_bytes())?;
-        cnt += w.write(b"\r\n\r\n")?;
-
-        cnt += w.write(&headers_bytes)?;
-        cnt += w.write(fr.body.as_ref())?;
-        cnt += w.write(b"\r\n\r\n")?;
-        w.flush()?;
-
-        Ok(cnt)
+    ) -> Result<WarcLocation> {
+        let mut block: Vec<u8> = Vec::new();
+        block.extend(fr.status_line().as_bytes());
+        let payload_len = fr.payload_digest.as_ref().map(|_| fr.body.len());
+        push_headers(&mut block, &archived_headers(headers, payload_len));
+
+        let original = match &fr.payload_digest {
+            Some(payload_digest) => db::select_first_capture(&mut self.conn, payload_digest)?,
+            None => None,
+        };
+        let response = match (&fr.payload_digest, original) {
+            // The block contains only the headers of the 304 response
+            (None, _) => Record::new("revisit", fr.start).field(
+                "WARC-Profile",
+                "http://netpreserve.org/warc/1.1/revisit/server-not-modified",
+            ),
+            // The payload has been archived before, only the headers are stored
+            (Some(payload_digest), Some((original, date))) => Record::new("revisit", fr.start)
+                .field(
+                    "WARC-Profile",
+                    "http://netpreserve.org/warc/1.1/revisit/identical-payload-digest",
+                )
+                .field("WARC-Refers-To-Target-URI", original.to_string())
+                .field("WARC-Refers-To-Date", warc::warc_date(date.into()))
+                .field("WARC-Payload-Digest", payload_digest.clone()),
+            (Some(payload_digest), None) => {
+                block.extend(&fr.body);
+                Record::new("response", fr.start)
+                    .field("WARC-Payload-Digest", payload_digest.clone())
+            }
+        };
+        let mut response = response
+            .field("WARC-Target-URI", url.to_string())
+            .field("Content-Type", "application/http; msgtype=response");
+        response.block = block;
+
+        // ureq only speaks HTTP/1.1
+        let mut block = format!("GET {} HTTP/1.1\r\n", request_target(url)).into_bytes();
+        push_headers(&mut block, request_headers);
+        let mut request = Record::new("request", fr.start)
+            .field("WARC-Target-URI", url.to_string())
+            .field("WARC-Concurrent-To", format!("<{}>", response.id))
+            .field("Content-Type", "application/http; msgtype=request");
+        request.block = block;
+
+        let mut locations = self.warc.write(&[&response, &request])?;
+        Ok(locations.swap_remove(0))
+    }
+}
+
+/// Appends the header lines and the empty line ending the header section
+fn push_headers(block: &mut Vec<u8>, headers: &HeaderMap) {
+    for (k, v) in headers {
+        block.extend(k.as_str().as_bytes());
+        block.extend(b": ");
+        block.extend(v.as_bytes());
+        block.extend(b"\r\n");
+    }
+    block.extend(b"\r\n");
+}
+
+/// Headers of a response as archived. ureq decodes the body, so the headers
+/// describing its encoding on the wire are renamed with the `X-Archive-Orig-`
+/// prefix of pywb. `Content-Length` is set to the length of the payload.
+fn archived_headers(headers: &HeaderMap, payload_len: Option<usize>) -> HeaderMap {
+    let payload_len = payload_len.map(HeaderValue::from);
+    let mut archived = HeaderMap::new();
+    for (name, value) in headers {
+        let renamed = match *name {
+            header::CONTENT_ENCODING | header::TRANSFER_ENCODING => true,
+            header::CONTENT_LENGTH => payload_len.as_ref().is_some_and(|len| len != value),
+            _ => false,
+        };
+        let name = if renamed {
+            match HeaderName::from_bytes(format!("x-archive-orig-{name}").as_bytes()) {
+                Ok(name) => name,
+                Err(_) => continue,
+            }
+        } else {
+            name.clone()
+        };
+        archived.append(name, value.clone());
+    }
+    if let Some(len) = payload_len {
+        archived.insert(header::CONTENT_LENGTH, len);
+    }
+    archived
+}
+
+/// Path and query of the url as sent in the request line
+fn request_target(url: &Url) -> String {
+    match url.query() {
+        Some(query) => format!("{}?{query}", url.path()),
+        None => url.path().to_string(),
+    }
+}
+
+/// Short classification of a failed fetch for the fetch log
+pub fn error_class(e: &anyhow::Error) -> &'static str {
+    let Some(e) = e.downcast_ref::<ureq::Error>() else {
+        return "other";
+    };
+    match e {
+        ureq::Error::Timeout(_) => "timeout",
+        ureq::Error::HostNotFound => "dns",
+        ureq::Error::ConnectionFailed => "connection",
+        ureq::Error::Tls(_) | ureq::Error::NativeTls(_) => "tls",
+        ureq::Error::Io(_) => "io",
+        ureq::Error::BodyExceedsLimit(_) => "body-too-large",
+        ureq::Error::Protocol(_) => "protocol",
+        ureq::Error::BadUri(_) => "bad-uri",
+        _ => "other",
+    }
+}
+
+/// `Retry-After` is either a number of seconds or an HTTP-date
+/// <https://httpwg.org/specs/rfc9110.html#field.retry-after>. Delays are
+/// limited to [`MAX_BACKOFF`].
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<SystemTime> {
+    let value = value.trim();
+    match value.parse::<u64>() {
+        Ok(seconds) => now.checked_add(Duration::from_secs(seconds).min(MAX_BACKOFF)),
+        Err(_) => httpdate::parse_http_date(value).ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{archived_headers, parse_retry_after, MAX_BACKOFF};
+    use http::{header, HeaderMap, HeaderValue};
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn archived_headers_of_decoded_body() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from_static("20"));
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/html"));
+        let archived = archived_headers(&headers, Some(42));
+        assert_eq!(archived["x-archive-orig-content-encoding"], "gzip");
+        assert_eq!(archived["x-archive-orig-content-length"], "20");
+        assert_eq!(archived[header::CONTENT_LENGTH], "42");
+        assert_eq!(archived[header::CONTENT_TYPE], "text/html");
+        assert!(!archived.contains_key(header::CONTENT_ENCODING));
+
+        let plain = archived_headers(&headers, Some(20));
+        assert!(!plain.contains_key("x-archive-orig-content-length"));
+        assert_eq!(plain[header::CONTENT_LENGTH], "20");
+
+        let not_modified = archived_headers(&headers, None);
+        assert_eq!(not_modified[header::CONTENT_LENGTH], "20");
+    }
+
+    #[test]
+    fn retry_after() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(784_111_777);
+        assert_eq!(
+            parse_retry_after("90", now),
+            Some(now + Duration::from_secs(90))
+        );
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT", now),
+            Some(now)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn retry_after_huge() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(784_111_777);
+        assert_eq!(
+            parse_retry_after("18446744073709551615", now),
+            Some(now + MAX_BACKOFF)
+        );
     }
 }
@@ -1,15 +1,663 @@
-use crate::crawler::{Inlink, Outlink};
+//! Parses syndication feeds: RSS 2.0, RSS 1.0 (RDF), Atom 1.0 and JSON Feed.
+//!
+//! - RSS 2.0: <https://www.rssboard.org/rss-specification>
+//! - RSS 1.0: <https://web.resource.org/rss/1.0/spec>
+//! - Atom: [RFC 4287](https://www.rfc-editor.org/rfc/rfc4287)
+//! - JSON Feed: <https://www.jsonfeed.org/version/1.1/>
+//! - Paged feeds: [RFC 5005](https://www.rfc-editor.org/rfc/rfc5005)
+
+use crate::crawler::{Context, Inlink, Outlink};
 use crate::url_util::is_http_s;
-use anyhow::Result;
-use url::{ParseError, Url};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use quick_xml::escape::{escape, resolve_xml_entity};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::reader::Reader;
+use serde_json::Value;
+use std::ops::Range;
+use std::str;
+use url::Url;
 
-pub(super) struct FeedExtractor;
+/// Media types of feeds announced in `<link rel="alternate" type="...">`
+/// and served as `Content-Type`
+pub const FEED_TYPES: [&str; 3] = [
+    "application/rss+xml",
+    "application/atom+xml",
+    "application/feed+json",
+];
 
-// todo: also extract img, script, style, ...
+/// Whether the media type, with or without parameters, is one of
+/// [`FEED_TYPES`]
+pub fn is_feed_type(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    FEED_TYPES.iter().any(|t| t.eq_ignore_ascii_case(mime))
+}
+
+/// Whether the body starts like a feed, for feeds served as `text/xml` or
+/// with another generic type
+pub fn looks_like_feed(body: &str) -> bool {
+    let body = body.trim_start_matches('\u{feff}').trim_start();
+    if body.starts_with('{') {
+        return body.contains("jsonfeed.org/version/");
+    }
+    let mut reader = Reader::from_str(body);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e) | Event::Empty(e)) => {
+                return matches!(e.local_name().as_ref(), b"rss" | b"RDF" | b"feed");
+            }
+            Ok(Event::Eof) | Err(_) => return false,
+            Ok(_) => (),
+        }
+    }
+}
+
+pub(super) struct FeedExtractor;
 
 impl super::Extractor for FeedExtractor {
-    fn get_outlinks(&self, _body_str: &str, _base: &Url) -> Result<Vec<Outlink>> {
-        debug!("todo: Feed extractor not yet done.");
-        Ok(Vec::new())
+    fn get_outlinks(&self, body_str: &str, base: &Url) -> Result<Vec<Outlink>> {
+        let feed = match parse(body_str, base) {
+            Ok(feed) => feed,
+            Err(e) => {
+                // e.g. an error page served with the type of the feed
+                warn!("Could not parse feed {base}: {e}");
+                return Ok(vec![]);
+            }
+        };
+        let mut outlinks: Vec<Outlink> = feed
+            .items
+            .into_iter()
+            .filter_map(|item| item.link)
+            .map(|url| Outlink {
+                url,
+                i: Inlink {
+                    context: Context::FeedLink,
+                    ..Inlink::default()
+                },
+            })
+            .collect();
+        // The next page of a paged or archived feed
+        if let Some(url) = feed.next {
+            outlinks.push(Outlink {
+                url,
+                i: Inlink {
+                    rel: Some(String::from("next")),
+                    context: Context::Feed,
+                    ..Inlink::default()
+                },
+            });
+        }
+        Ok(outlinks)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Feed {
+    pub title: Option<String>,
+    /// Web site of the feed
+    pub home: Option<Url>,
+    /// Next page of a paged or archived feed, `rel="next"`
+    pub next: Option<Url>,
+    pub items: Vec<FeedItem>,
+}
+
+#[derive(Debug, Default)]
+pub struct FeedItem {
+    pub title: Option<String>,
+    /// RSS guid, Atom or JSON Feed id
+    pub id: Option<String>,
+    pub link: Option<Url>,
+    pub published: Option<DateTime<Utc>>,
+    pub updated: Option<DateTime<Utc>>,
+    pub author: Option<String>,
+    /// Summary or description, may contain HTML
+    pub summary: Option<String>,
+    /// Full content, HTML
+    pub content: Option<String>,
+}
+
+/// Parses a feed in any of the supported formats. Relative links are
+/// resolved against the url of the feed.
+pub fn parse(body: &str, base: &Url) -> Result<Feed> {
+    let body = body.trim_start_matches('\u{feff}').trim_start();
+    if body.starts_with('{') {
+        return parse_json(&serde_json::from_str(body)?, base);
+    }
+    let root = parse_xml(body)?;
+    match root.name.as_str() {
+        "rss" => Ok(rss(&root, base)),
+        "RDF" => Ok(rdf(&root, base)),
+        "feed" => Ok(atom(&root, body, base)),
+        name => bail!("Not a feed, root element: {name}"),
+    }
+}
+
+/// XML element with its local name, namespace prefixes are ignored. Text is
+/// kept in document order: `text` precedes the first child, the `tail` of
+/// each child follows it.
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    text: String,
+    tail: String,
+    children: Vec<Element>,
+    /// Byte range of the content between the start and the end tag
+    inner: Range<usize>,
+}
+
+impl Element {
+    fn new(start: &BytesStart) -> Self {
+        let attributes = start
+            .attributes()
+            .flatten()
+            .filter_map(|a| {
+                let key = str::from_utf8(a.key.local_name().as_ref())
+                    .ok()?
+                    .to_string();
+                Some((key, a.unescape_value().ok()?.into_owned()))
+            })
+            .collect();
+        Element {
+            name: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(),
+            attributes,
+            ..Element::default()
+        }
+    }
+
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+
+    fn child<'a>(&'a self, name: &'a str) -> Option<&'a Element> {
+        self.children(name).next()
+    }
+
+    /// Trimmed text of the first child with the name, None if empty
+    fn child_text(&self, name: &str) -> Option<String> {
+        self.child(name).and_then(Element::all_text)
+    }
+
+    /// Trimmed text of the element and its descendants, None if empty
+    fn all_text(&self) -> Option<String> {
+        fn collect(e: &Element, s: &mut String) {
+            s.push_str(&e.text);
+            for c in &e.children {
+                collect(c, s);
+                s.push_str(&c.tail);
+            }
+        }
+        let mut s = String::new();
+        collect(self, &mut s);
+        let s = s.trim();
+        (!s.is_empty()).then(|| s.to_string())
+    }
+
+    /// Trimmed source of the content with its markup, None if empty.
+    /// `body` is the document the element was parsed from.
+    fn inner_xml(&self, body: &str) -> Option<String> {
+        let s = body.get(self.inner.clone())?.trim();
+        (!s.is_empty()).then(|| s.to_string())
+    }
+}
+
+/// Parses XML into a tree and returns the root element
+fn parse_xml(body: &str) -> Result<Element> {
+    let mut reader = Reader::from_str(body);
+    let mut stack: Vec<Element> = Vec::new();
+    loop {
+        let before = usize::try_from(reader.buffer_position())?;
+        match reader.read_event()? {
+            Event::Start(e) => {
+                let mut element = Element::new(&e);
+                let after = usize::try_from(reader.buffer_position())?;
+                element.inner = after..after;
+                stack.push(element);
+            }
+            Event::Empty(e) => {
+                let element = Element::new(&e);
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            Event::End(_) => {
+                let mut element = stack.pop().ok_or_else(|| anyhow!("Unbalanced end tag"))?;
+                element.inner.end = before;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            Event::Text(e) => push_text(&mut stack, &unescape(&String::from_utf8_lossy(&e))),
+            Event::CData(e) => push_text(&mut stack, &String::from_utf8_lossy(&e)),
+            Event::Eof => bail!("No root element"),
+            _ => (),
+        }
+    }
+}
+
+/// Appends text to the open element, after its last child if there is one
+fn push_text(stack: &mut [Element], text: &str) {
+    if let Some(current) = stack.last_mut() {
+        match current.children.last_mut() {
+            Some(child) => child.tail.push_str(text),
+            None => current.text.push_str(text),
+        }
+    }
+}
+
+/// Resolves the predefined XML entities and character references. Entities
+/// undefined in XML like `&nbsp;` are kept as they are.
+fn unescape(raw: &str) -> String {
+    let resolve = |entity: &str| -> Option<String> {
+        let Some(number) = entity.strip_prefix('#') else {
+            return resolve_xml_entity(entity).map(String::from);
+        };
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16),
+            None => number.parse(),
+        };
+        code.ok().and_then(char::from_u32).map(String::from)
+    };
+    let mut unescaped = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let resolved = rest
+            .find(';')
+            .and_then(|end| Some((resolve(&rest[1..end])?, end + 1)));
+        if let Some((value, len)) = resolved {
+            unescaped.push_str(&value);
+            rest = &rest[len..];
+        } else {
+            unescaped.push('&');
+            rest = &rest[1..];
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+fn rss(root: &Element, base: &Url) -> Feed {
+    let Some(channel) = root.child("channel") else {
+        return Feed::default();
+    };
+    Feed {
+        title: channel.child_text("title"),
+        // <link> of RSS has text, <atom:link> has a href
+        home: channel
+            .children("link")
+            .find_map(Element::all_text)
+            .and_then(|l| resolve(&l, base)),
+        next: atom_link(channel, "next", base),
+        items: channel
+            .children("item")
+            .map(|item| FeedItem {
+                title: item.child_text("title"),
+                id: item.child_text("guid"),
+                link: item
+                    .children("link")
+                    .find_map(Element::all_text)
+                    .and_then(|l| resolve(&l, base))
+                    .or_else(|| permalink(item, base)),
+                published: item
+                    .child_text("pubDate")
+                    .or_else(|| item.child_text("date"))
+                    .and_then(|d| parse_date(&d)),
+                updated: item.child_text("updated").and_then(|d| parse_date(&d)),
+                author: item
+                    .child_text("creator")
+                    .or_else(|| item.child_text("author")),
+                summary: item.child_text("description"),
+                // content:encoded
+                content: item.child_text("encoded"),
+            })
+            .collect(),
+    }
+}
+
+/// RSS guid that is a permalink, the default
+fn permalink(item: &Element, base: &Url) -> Option<Url> {
+    let guid = item.child("guid")?;
+    if guid.attr("isPermaLink") == Some("false") {
+        return None;
+    }
+    resolve(&guid.all_text()?, base)
+}
+
+fn rdf(root: &Element, base: &Url) -> Feed {
+    let channel = root.child("channel");
+    Feed {
+        title: channel.and_then(|c| c.child_text("title")),
+        home: channel
+            .and_then(|c| c.child_text("link"))
+            .and_then(|l| resolve(&l, base)),
+        next: None,
+        // Items are siblings of the channel
+        items: root
+            .children("item")
+            .map(|item| FeedItem {
+                title: item.child_text("title"),
+                id: item.attr("about").map(String::from),
+                link: item.child_text("link").and_then(|l| resolve(&l, base)),
+                published: item.child_text("date").and_then(|d| parse_date(&d)),
+                updated: None,
+                author: item.child_text("creator"),
+                summary: item.child_text("description"),
+                content: item.child_text("encoded"),
+            })
+            .collect(),
+    }
+}
+
+/// `body` is the source of the feed, for the markup of XHTML content
+fn atom(feed: &Element, body: &str, base: &Url) -> Feed {
+    Feed {
+        title: feed.child_text("title"),
+        home: atom_link(feed, "alternate", base),
+        next: atom_link(feed, "next", base),
+        items: feed
+            .children("entry")
+            .map(|entry| FeedItem {
+                title: entry.child_text("title"),
+                id: entry.child_text("id"),
+                link: atom_link(entry, "alternate", base),
+                published: entry.child_text("published").and_then(|d| parse_date(&d)),
+                updated: entry.child_text("updated").and_then(|d| parse_date(&d)),
+                author: entry
+                    .child("author")
+                    .and_then(|a| a.child_text("name"))
+                    .or_else(|| feed.child("author").and_then(|a| a.child_text("name"))),
+                summary: entry.child("summary").and_then(|s| atom_text(s, body)),
+                // Content with `src` is not part of the feed
+                content: entry
+                    .child("content")
+                    .filter(|c| c.attr("src").is_none())
+                    .and_then(|c| atom_text(c, body)),
+            })
+            .collect(),
+    }
+}
+
+/// `href` of the first `<link>` with the relation, links without `rel`
+/// are `alternate`
+fn atom_link(parent: &Element, rel: &str, base: &Url) -> Option<Url> {
+    parent
+        .children("link")
+        .filter(|l| l.attr("rel").unwrap_or("alternate") == rel)
+        .find_map(|l| l.attr("href"))
+        .and_then(|href| resolve(href, base))
+}
+
+/// HTML of an Atom text construct or content. XHTML is wrapped in a `div`
+/// that is not part of the content, plain text is escaped.
+fn atom_text(e: &Element, body: &str) -> Option<String> {
+    match e.attr("type").unwrap_or("text") {
+        "xhtml" => e.child("div").and_then(|div| div.inner_xml(body)),
+        "text" | "text/plain" => e.all_text().map(|t| escape(&t).into_owned()),
+        _ => e.all_text(),
+    }
+}
+
+fn parse_json(json: &Value, base: &Url) -> Result<Feed> {
+    if !json["version"]
+        .as_str()
+        .is_some_and(|v| v.starts_with("https://jsonfeed.org/version/"))
+    {
+        bail!("Not a JSON Feed");
+    }
+    let text = |v: &Value| {
+        v.as_str()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+    };
+    let link = |v: &Value| v.as_str().and_then(|l| resolve(l, base));
+    let author = |v: &Value| {
+        v["authors"]
+            .as_array()
+            .and_then(|a| a.first())
+            .or(v.get("author"))
+            .and_then(|a| text(&a["name"]))
+    };
+    let items = json["items"].as_array().map_or_else(Vec::new, |items| {
+        items
+            .iter()
+            .map(|item| FeedItem {
+                title: text(&item["title"]),
+                id: text(&item["id"]).or_else(|| item["id"].as_i64().map(|i| i.to_string())),
+                link: link(&item["url"]).or_else(|| link(&item["external_url"])),
+                published: item["date_published"].as_str().and_then(parse_date),
+                updated: item["date_modified"].as_str().and_then(parse_date),
+                author: author(item).or_else(|| author(json)),
+                summary: text(&item["summary"]),
+                content: text(&item["content_html"])
+                    .or_else(|| text(&item["content_text"]).map(|t| escape(&t).into_owned())),
+            })
+            .collect()
+    });
+    Ok(Feed {
+        title: text(&json["title"]),
+        home: link(&json["home_page_url"]),
+        next: link(&json["next_url"]),
+        items,
+    })
+}
+
+/// Http(s) url of the link relative to the feed
+fn resolve(link: &str, base: &Url) -> Option<Url> {
+    base.join(link.trim()).ok().filter(is_http_s)
+}
+
+/// RFC 3339 (Atom, JSON Feed, Dublin Core), RFC 2822 (RSS 2.0) or a plain
+/// date
+fn parse_date(date: &str) -> Option<DateTime<Utc>> {
+    let date = date.trim();
+    DateTime::parse_from_rfc3339(date)
+        .or_else(|_| DateTime::parse_from_rfc2822(date))
+        .map(|d| d.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .map(|d| d.and_utc())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{looks_like_feed, parse};
+    use url::Url;
+
+    fn base() -> Url {
+        Url::parse("https://example.org/feed.xml").unwrap()
+    }
+
+    #[test]
+    fn rss2() {
+        let feed = parse(
+            r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:content="http://purl.org/rss/1.0/modules/content/">
+<channel>
+  <title>Example &amp; Co</title>
+  <link>https://example.org/</link>
+  <atom:link rel="self" href="https://example.org/feed.xml"/>
+  <atom:link rel="next" href="/feed.xml?page=2"/>
+  <item>
+    <title>First</title>
+    <link>/posts/1</link>
+    <guid isPermaLink="false">post-1</guid>
+    <pubDate>Sun, 18 Oct 2026 12:00:00 +0200</pubDate>
+    <dc:creator>Jane</dc:creator>
+    <description><![CDATA[<p>Hello</p>]]></description>
+    <content:encoded><![CDATA[<p>Hello <em>world</em></p>]]></content:encoded>
+  </item>
+  <item><guid>https://example.org/posts/2</guid></item>
+</channel>
+</rss>"#,
+            &base(),
+        )
+        .unwrap();
+        assert_eq!(feed.title.as_deref(), Some("Example & Co"));
+        assert_eq!(feed.home.unwrap().as_str(), "https://example.org/");
+        assert_eq!(
+            feed.next.unwrap().as_str(),
+            "https://example.org/feed.xml?page=2"
+        );
+        let item = &feed.items[0];
+        assert_eq!(item.title.as_deref(), Some("First"));
+        assert_eq!(item.id.as_deref(), Some("post-1"));
+        assert_eq!(
+            item.link.as_ref().unwrap().as_str(),
+            "https://example.org/posts/1"
+        );
+        assert_eq!(
+            item.published.unwrap().to_rfc3339(),
+            "2026-10-18T10:00:00+00:00"
+        );
+        assert_eq!(item.author.as_deref(), Some("Jane"));
+        assert_eq!(item.summary.as_deref(), Some("<p>Hello</p>"));
+        assert_eq!(item.content.as_deref(), Some("<p>Hello <em>world</em></p>"));
+        assert_eq!(
+            feed.items[1].link.as_ref().unwrap().as_str(),
+            "https://example.org/posts/2"
+        );
+    }
+
+    #[test]
+    fn rss1() {
+        let feed = parse(
+            r#"<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#" xmlns="http://purl.org/rss/1.0/" xmlns:dc="http://purl.org/dc/elements/1.1/">
+  <channel rdf:about="https://example.org/"><title>RDF</title><link>https://example.org/</link></channel>
+  <item rdf:about="https://example.org/a">
+    <title>A</title><link>https://example.org/a</link><dc:date>2026-10-18</dc:date>
+  </item>
+</rdf:RDF>"#,
+            &base(),
+        )
+        .unwrap();
+        assert_eq!(feed.title.as_deref(), Some("RDF"));
+        let item = &feed.items[0];
+        assert_eq!(item.id.as_deref(), Some("https://example.org/a"));
+        assert_eq!(
+            item.published.unwrap().to_rfc3339(),
+            "2026-10-18T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn atom() {
+        let feed = parse(
+            r#"<feed xmlns="http://www.w3.org/2005/Atom">
+  <title type="text">Atom</title>
+  <link href="https://example.org/"/>
+  <link rel="next" href="https://example.org/atom?page=2"/>
+  <author><name>Feed Author</name></author>
+  <entry>
+    <title>Entry</title>
+    <id>urn:uuid:1</id>
+    <link rel="alternate" href="/entry"/>
+    <link rel="enclosure" href="/entry.mp3"/>
+    <published>2026-10-17T12:00:00Z</published>
+    <updated>2026-10-18T12:00:00Z</updated>
+    <summary>1 &lt; 2</summary>
+    <content type="xhtml"><div xmlns="http://www.w3.org/1999/xhtml">
+      <p>Some <b>text</b> &amp; <a href="/more">more</a></p>
+    </div></content>
+  </entry>
+</feed>"#,
+            &base(),
+        )
+        .unwrap();
+        assert_eq!(feed.home.unwrap().as_str(), "https://example.org/");
+        assert_eq!(
+            feed.next.unwrap().as_str(),
+            "https://example.org/atom?page=2"
+        );
+        let entry = &feed.items[0];
+        assert_eq!(entry.id.as_deref(), Some("urn:uuid:1"));
+        assert_eq!(
+            entry.link.as_ref().unwrap().as_str(),
+            "https://example.org/entry"
+        );
+        assert_eq!(entry.author.as_deref(), Some("Feed Author"));
+        assert_eq!(entry.summary.as_deref(), Some("1 &lt; 2"));
+        assert_eq!(
+            entry.content.as_deref(),
+            Some(r#"<p>Some <b>text</b> &amp; <a href="/more">more</a></p>"#)
+        );
+        assert!(entry.published < entry.updated);
+    }
+
+    #[test]
+    fn json_feed() {
+        let feed = parse(
+            r#"{
+  "version": "https://jsonfeed.org/version/1.1",
+  "title": "JSON",
+  "home_page_url": "https://example.org/",
+  "next_url": "https://example.org/feed.json?page=2",
+  "items": [
+    {"id": "1", "url": "https://example.org/1", "title": "One",
+     "content_text": "Text", "date_published": "2026-10-18T12:00:00+02:00",
+     "authors": [{"name": "Jane"}]}
+  ]
+}"#,
+            &base(),
+        )
+        .unwrap();
+        assert_eq!(feed.title.as_deref(), Some("JSON"));
+        assert!(feed.next.is_some());
+        let item = &feed.items[0];
+        assert_eq!(item.id.as_deref(), Some("1"));
+        assert_eq!(item.summary, None);
+        assert_eq!(item.content.as_deref(), Some("Text"));
+        assert_eq!(item.author.as_deref(), Some("Jane"));
+        assert!(parse("{}", &base()).is_err());
+        assert!(parse("<html></html>", &base()).is_err());
+    }
+
+    #[test]
+    fn sloppy_feeds() {
+        let feed = parse(
+            "<rss><channel><title>A&nbsp;&amp;&nbsp;B</title></channel></rss>",
+            &base(),
+        )
+        .unwrap();
+        assert_eq!(feed.title.as_deref(), Some("A&nbsp;&&nbsp;B"));
+        let feed = parse(
+            "<rss><channel><title>Rock &#x26;&#38; Roll&#8217;s <b>best</b> of&nbsp;</title></channel></rss>",
+            &base(),
+        )
+        .unwrap();
+        assert_eq!(
+            feed.title.as_deref(),
+            Some("Rock && Roll\u{2019}s best of&nbsp;")
+        );
+        assert!(parse("<rss><channel></item></channel></rss>", &base()).is_err());
+
+        assert!(looks_like_feed(
+            "\u{feff}<?xml version=\"1.0\"?>\n<!-- generated -->\n<feed xmlns=\"http://www.w3.org/2005/Atom\">"
+        ));
+        assert!(looks_like_feed(
+            "<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">"
+        ));
+        assert!(looks_like_feed(
+            r#"{"version": "https://jsonfeed.org/version/1.1", "items": []}"#
+        ));
+        assert!(!looks_like_feed(
+            "<!DOCTYPE html><html><body><rss></rss></body></html>"
+        ));
+        assert!(!looks_like_feed(r#"{"items": []}"#));
     }
 }
@@ -1,4 +1,5 @@
-use crate::crawler::{Inlink, Outlink};
+use super::feed::is_feed_type;
+use crate::crawler::{Context, Inlink, Outlink};
 use crate::url_util::is_http_s;
 use anyhow::Result;
 use select::document::Document;
@@ -22,31 +23,10 @@ impl super::Extractor for HtmlExtractor {
                 continue;
             };
 
-            if let Some(mut url) = match Url::parse(href) {
-                Ok(url) if is_http_s(&url) => Some(url),
-                Ok(_) => None,
-                Err(ParseError::RelativeUrlWithoutBase) => match base.join(href) {
-                    Ok(url) => Some(url),
-                    Err(err) => {
-                        debug!("{:?}: {href}", err);
-                        None
-                    }
-                },
-                Err(err) => {
-                    debug!("{:?}: {href}", err);
-                    None
-                }
-            } {
-                if url.fragment().is_some() {
-                    url.set_fragment(None);
-                }
+            if let Some(url) = resolve(href, base) {
                 if url.to_string() == base.to_string() {
                     continue;
                 }
-                // TODO remove safeguard!!!
-                if url.host_str() != Some("de.populus.wiki") {
-                    continue;
-                }
                 outlinks.push(Outlink {
                     url,
                     i: Inlink {
@@ -56,6 +36,87 @@ impl super::Extractor for HtmlExtractor {
                 });
             }
         }
+        outlinks.append(&mut feed_links(&document, base));
         Ok(outlinks)
     }
 }
+
+/// Feeds announced with
+/// `<link rel="alternate" type="application/rss+xml" href="...">`
+fn feed_links(document: &Document, base: &Url) -> Vec<Outlink> {
+    document
+        .find(Name("link").and(Attr("rel", ())).and(Attr("href", ())))
+        .filter(|node| {
+            node.attr("rel").is_some_and(|rel| {
+                rel.split_ascii_whitespace()
+                    .any(|r| r.eq_ignore_ascii_case("alternate"))
+            }) && node.attr("type").is_some_and(is_feed_type)
+        })
+        .filter_map(|node| resolve(node.attr("href")?, base))
+        .map(|url| Outlink {
+            url,
+            i: Inlink {
+                rel: Some(String::from("alternate")),
+                context: Context::Feed,
+                ..Inlink::default()
+            },
+        })
+        .collect()
+}
+
+/// Http(s) url of the link without fragment
+fn resolve(href: &str, base: &Url) -> Option<Url> {
+    let mut url = match Url::parse(href) {
+        Ok(url) if is_http_s(&url) => url,
+        Ok(_) => return None,
+        Err(ParseError::RelativeUrlWithoutBase) => match base.join(href) {
+            Ok(url) => url,
+            Err(err) => {
+                debug!("{err:?}: {href}");
+                return None;
+            }
+        },
+        Err(err) => {
+            debug!("{err:?}: {href}");
+            return None;
+        }
+    };
+    if url.fragment().is_some() {
+        url.set_fragment(None);
+    }
+    Some(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HtmlExtractor;
+    use crate::crawler::Context;
+    use crate::link_extractor::Extractor;
+    use url::Url;
+
+    #[test]
+    fn discover_feeds() {
+        let html = r#"<html><head>
+<link rel="stylesheet" href="/style.css">
+<link rel="alternate" type="application/rss+xml" title="RSS" href="/rss.xml">
+<link rel="Alternate" type="application/atom+xml" href="https://feeds.example.com/atom">
+<link rel="alternate" hreflang="de" href="/de/">
+</head><body><a href="/about#team">About</a></body></html>"#;
+        let base = Url::parse("https://example.org/").unwrap();
+        let outlinks = HtmlExtractor.get_outlinks(html, &base).unwrap();
+        let feeds: Vec<&str> = outlinks
+            .iter()
+            .filter(|o| matches!(o.i.context, Context::Feed))
+            .map(|o| o.url.as_str())
+            .collect();
+        assert_eq!(
+            feeds,
+            [
+                "https://example.org/rss.xml",
+                "https://feeds.example.com/atom"
+            ]
+        );
+        assert_eq!(outlinks[0].url.as_str(), "https://example.org/about");
+        assert_eq!(outlinks.len(), 3);
+    }
+}
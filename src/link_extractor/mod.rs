@@ -12,15 +12,16 @@ use sitemap::SitemapExtractor;
 use anyhow::Result;
 use url::Url;
 
-mod feed;
+pub mod feed;
 mod html;
 mod sitemap;
 
 pub fn extract_outlinks(item: &UrlItem, fr: &FetchResult) -> Result<Vec<Outlink>> {
     let inlink = get_inlink(&item.i);
     // todo: also extract links from headers: Feeds, pagination next, script, style, ...
-    let extractor = get_extractor(fr, &inlink);
-    extractor.get_outlinks(fr.body_str().as_ref(), &item.url)
+    let body = fr.body_str();
+    let extractor = get_extractor(fr, &body, &inlink);
+    extractor.get_outlinks(&body, &item.url)
     // Doesn't seem worthwile to look for links in HTTP header
     // https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Link
 }
@@ -33,13 +34,17 @@ fn get_inlink(links: &[Inlink]) -> Inlink {
     links[0].clone()
 }
 
-fn get_extractor(_fr: &FetchResult, inlink: &Inlink) -> Box<dyn Extractor> {
+fn get_extractor(fr: &FetchResult, body: &str, inlink: &Inlink) -> Box<dyn Extractor> {
     // todo context alone is not enough to get the right extractor!
+    // The context is lost for urls selected from the DB, feeds served as
+    // text/xml are recognized by their root element.
+    if fr.content_type.as_deref().is_some_and(feed::is_feed_type) || feed::looks_like_feed(body) {
+        return Box::new(FeedExtractor);
+    }
     match inlink.context {
-        Context::Other => Box::new(HtmlExtractor),
         Context::Sitemap => Box::new(SitemapExtractor),
         Context::Feed => Box::new(FeedExtractor),
-        _ => todo!(),
+        _ => Box::new(HtmlExtractor),
     }
 }
 
@@ -39,10 +39,8 @@ impl super::Extractor for SitemapExtractor {
                         }
                     }
                 },
-                Ok(Event::Text(e)) => {
-                    if in_entry {
-                        entry.insert(key.to_string(), e.unescape().unwrap().into_owned());
-                    }
+                Ok(Event::Text(e)) if in_entry => {
+                    entry.insert(key.clone(), e.unescape().unwrap().into_owned());
                 }
                 Ok(Event::End(e)) => {
                     if let entry_name @ (b"url" | b"sitemap") = e.name().as_ref() {
@@ -76,12 +74,14 @@ fn entry_to_outlink(entry: &HashMap<String, String>, context: Context) -> Option
     };
 
     // TODO also use the other data, especially lastmod!
-    // https://doc.rust-lang.org/std/primitive.f32.html#method.clamp for sanitizing sitemap provided priority values
+    // The priority is sanitized by the url frontier.
+    let priority = entry.get("priority").and_then(|p| p.trim().parse().ok());
 
     Some(Outlink {
         url,
         i: Inlink {
             context,
+            priority,
             ..Inlink::default()
         },
     })
@@ -4,6 +4,24 @@
 //! - respects robots.txt
 //! - checks sitemaps.xml
 //! - writes WARC archive of crawled urls
+//!
+//! Commands:
+//!
+//! - `crawl` (default): work on crawl jobs until none is left
+//! - `add-job [--max-depth N] [--scope RULE]... SEED...`: add a crawl job
+//! - `index [WARC_FILE...]`: rebuild the CDXJ index of WARC files, by default
+//!   of all files in `ARCHIVE_DIR`
+//! - `verify [WARC_FILE...]`: check records and digests of WARC files
+//! - `export-wacz JOB_ID [FILE]`: export the WARC files of a crawl job as
+//!   WACZ file
+//! - `replay [ADDR]`: serve the archive and Atom feeds of the subscribed
+//!   feeds over HTTP, by default on `127.0.0.1:8080`
+//! - `subscribe [--interval MINUTES] FEED_URL...`: subscribe to feeds, polled
+//!   every hour by default
+//! - `update-feeds`: poll subscribed feeds when they are due until interrupted
+//! - `import-opml [--max-depth N] FILE`: subscribe to the feeds of an OPML
+//!   file and crawl their homepages
+//! - `export-opml [FILE]`: export the subscriptions as OPML
 
 #![warn(clippy::all, clippy::pedantic)]
 #![warn(missing_docs)]
@@ -15,26 +33,37 @@
 extern crate log;
 
 mod clock;
+mod crawl_job;
 mod crawler;
 #[macro_use]
 mod env_vars;
 mod db;
+mod feed_reader;
 mod fetcher;
 mod link_extractor;
+mod politeness;
+mod replay;
 mod robotstxt;
 mod signal_handler;
 mod url_frontier;
 mod url_util;
+mod warc;
 
-use std::thread;
+use anyhow::anyhow;
+use env_config::CRAWL_WORKERS;
 
 env_vars![
     ARCHIVE_DIR
     BOT_NAME
     DB_URL
     FROM // https://httpwg.org/specs/rfc9110.html#field.from
+    ;
+    CRAWL_WORKERS // number of crawler threads
+    WARC_MAX_SIZE // bytes after which a new WARC file is started
 ];
 
+const DEFAULT_CRAWL_WORKERS: usize = 8;
+
 fn main() {
     env_logger::init();
     info!("starting up");
@@ -42,15 +71,30 @@ fn main() {
     env_config::check();
     info!("Configuration: {:?}", env_config::get_map());
 
-    let t = thread::spawn(move || {
-        let signal_handler = signal_handler::SignalHandler::register();
-
-        let mut crawler = crawler::Crawler::new(signal_handler).expect("error");
-        if let Err(e) = crawler.run() {
-            error!("{e:?}");
-        }
-    });
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        None | Some("crawl") => crawl(),
+        Some("add-job") => crawl_job::add(&args[1..]),
+        Some("index") => warc::cdxj::rebuild(&args[1..]),
+        Some("verify") => warc::reader::verify(&args[1..]),
+        Some("export-wacz") => warc::wacz::export(&args[1..]),
+        Some("replay") => replay::serve(&args[1..]),
+        Some("subscribe") => feed_reader::subscribe(&args[1..]),
+        Some("import-opml") => feed_reader::opml::import(&args[1..]),
+        Some("export-opml") => feed_reader::opml::export(&args[1..]),
+        Some("update-feeds") => feed_reader::update(&signal_handler::SignalHandler::register()),
+        Some(command) => Err(anyhow!("Unknown command: {command}")),
+    };
+    if let Err(e) = result {
+        error!("{e:?}");
+        std::process::exit(1);
+    }
+}
 
-    info!("waiting for crawler");
-    let _ = t.join();
+fn crawl() -> anyhow::Result<()> {
+    let signal_handler = signal_handler::SignalHandler::register();
+    crawler::crawl(
+        CRAWL_WORKERS.parse_or(DEFAULT_CRAWL_WORKERS),
+        &signal_handler,
+    )
 }
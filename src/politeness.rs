@@ -0,0 +1,152 @@
+//! Time to wait between two fetches from the same host. Shared by the fetchers
+//! of all workers, which wait before each fetch and never fetch from the same
+//! host at the same time, and the url frontier, which prefers hosts that are
+//! ready to be fetched from.
+
+use crate::clock;
+use crate::fetcher::FetchResult;
+use simple_moving_average::{NoSumSMA, SMA};
+use std::collections::HashMap;
+use std::ops::Add;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+const MIN_FETCH_DURATION: Duration = Duration::from_millis(150); // We actually wait 3xavg fetch duration between fetches
+/// Back-off after the first 429 or 503 response without Retry-After header,
+/// doubled on every repetition
+const MIN_BACKOFF: Duration = Duration::from_mins(1);
+/// Upper bound for back-off and Retry-After
+pub const MAX_BACKOFF: Duration = Duration::from_secs(clock::ONE_DAY);
+/// Interval to check whether another worker finished fetching from a host
+pub const IN_FLIGHT_POLL: Duration = Duration::from_millis(50);
+
+pub type SharedPoliteness = Arc<Mutex<PolitenessMap>>;
+
+struct Politeness {
+    until: SystemTime,
+    duration_avg: NoSumSMA<Duration, u32, 10>,
+    /// Number of consecutive 429 or 503 responses
+    backoff_cnt: u32,
+    /// A worker is fetching from this host right now
+    in_flight: bool,
+}
+
+impl Default for Politeness {
+    fn default() -> Self {
+        Politeness {
+            until: SystemTime::UNIX_EPOCH,
+            duration_avg: NoSumSMA::<Duration, u32, 10>::from_zero(Duration::ZERO),
+            backoff_cnt: 0,
+            in_flight: false,
+        }
+    }
+}
+
+impl Politeness {
+    pub fn update(&mut self, fr: &FetchResult) {
+        self.in_flight = false;
+        match fr.status.as_u16() {
+            200 | 304 => {
+                self.duration_avg
+                    .add_sample(std::cmp::max(fr.duration, MIN_FETCH_DURATION));
+                self.until = fr.start.add(self.duration_avg.get_average() * 3);
+                self.backoff_cnt = 0;
+            }
+            429 | 503 => {
+                let backoff = MIN_BACKOFF
+                    .saturating_mul(2_u32.saturating_pow(self.backoff_cnt))
+                    .min(MAX_BACKOFF);
+                self.backoff_cnt += 1;
+                let until = fr.start.add(backoff);
+                self.until = match fr.retry_after {
+                    Some(retry_after) => retry_after.clamp(until, fr.start.add(MAX_BACKOFF)),
+                    None => until,
+                };
+                info!(
+                    "Backing off for {}s after status {}",
+                    self.until
+                        .duration_since(fr.start)
+                        .unwrap_or_default()
+                        .as_secs(),
+                    fr.status
+                );
+            }
+            _ => {
+                let avg = std::cmp::max(self.duration_avg.get_average(), MIN_FETCH_DURATION);
+                self.until = fr.start.add(avg * 3);
+            }
+        }
+    }
+}
+
+/// Politeness of all hosts by authority
+#[derive(Default)]
+pub struct PolitenessMap {
+    hosts: HashMap<String, Politeness>,
+}
+
+impl PolitenessMap {
+    pub fn new_shared() -> SharedPoliteness {
+        Arc::new(Mutex::new(Self::default()))
+    }
+
+    /// Earliest time for the next fetch from the host
+    pub fn until(&self, authority: &str) -> SystemTime {
+        self.hosts
+            .get(authority)
+            .map_or(SystemTime::UNIX_EPOCH, |p| p.until)
+    }
+
+    /// Reserves the host for one fetch and returns the earliest time for it.
+    /// Returns None while another worker is fetching from the host.
+    pub fn acquire(&mut self, authority: &str) -> Option<SystemTime> {
+        let p = self.hosts.entry(authority.to_string()).or_default();
+        if p.in_flight {
+            return None;
+        }
+        p.in_flight = true;
+        Some(p.until)
+    }
+
+    /// Ends the reservation of a fetch that got no response
+    pub fn release(&mut self, authority: &str) {
+        if let Some(p) = self.hosts.get_mut(authority) {
+            p.in_flight = false;
+        }
+    }
+
+    /// Ends the reservation of the host and updates its waiting time
+    pub fn update(&mut self, authority: &str, fr: &FetchResult) {
+        // todo clean old entries
+        self.hosts
+            .entry(authority.to_string())
+            .or_default()
+            .update(fr);
+    }
+
+    /// Returns the minimal waiting time of the given hosts and the hosts with
+    /// this waiting time, the longest idle host first. Unknown hosts don't
+    /// need to wait.
+    pub fn get_domains_min_waiting<'a>(
+        &self,
+        authorities: impl IntoIterator<Item = &'a str>,
+    ) -> (Duration, Vec<&'a str>) {
+        let mut hosts: Vec<(&str, SystemTime)> = authorities
+            .into_iter()
+            .map(|a| (a, self.until(a)))
+            .collect();
+        hosts.sort_by_key(|(_, until)| *until);
+        let now = SystemTime::now();
+        let waiting = |until: SystemTime| until.duration_since(now).unwrap_or_default();
+        let Some((_, first)) = hosts.first() else {
+            return (Duration::ZERO, vec![]);
+        };
+        let min_waiting = waiting(*first);
+        let hosts = hosts
+            .into_iter()
+            .take_while(|(_, until)| waiting(*until) == min_waiting)
+            .map(|(a, _)| a)
+            .collect();
+        (min_waiting, hosts)
+    }
+}
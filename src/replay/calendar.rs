@@ -0,0 +1,119 @@
+//! Calendar of the captures of a url: one table per month with captures,
+//! days with captures link to the first capture of the day.
+
+use super::{escape, PREFIX};
+use crate::warc::cdxj::Capture;
+use chrono::{Datelike, Months, NaiveDate};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+pub const STYLE: &str = "body { font-family: sans-serif } \
+    table { display: inline-table; margin: 0 1em 1em 0; border-collapse: collapse } \
+    caption { font-weight: bold } td, th { padding: 0.2em 0.4em; text-align: right } \
+    td a { font-weight: bold }";
+
+const WEEKDAYS: [&str; 7] = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"];
+
+/// Calendar of the captures, oldest first
+pub fn html(captures: &[Capture]) -> String {
+    // (year, month) -> day -> captures of the day
+    let mut months: BTreeMap<(i32, u32), BTreeMap<u32, Vec<&Capture>>> = BTreeMap::new();
+    for capture in captures {
+        let Some(date) = date(&capture.timestamp) else {
+            continue;
+        };
+        months
+            .entry((date.year(), date.month()))
+            .or_default()
+            .entry(date.day())
+            .or_default()
+            .push(capture);
+    }
+
+    let mut html = String::new();
+    if let (Some(first), Some(last)) = (captures.first(), captures.last()) {
+        let _ = writeln!(
+            html,
+            "<p>{} captures between {} and {}</p>",
+            captures.len(),
+            display(&first.timestamp),
+            display(&last.timestamp)
+        );
+    }
+    let mut year = None;
+    for ((y, m), days) in &months {
+        if year != Some(*y) {
+            year = Some(*y);
+            let _ = writeln!(html, "<h2>{y}</h2>");
+        }
+        month(&mut html, *y, *m, days);
+    }
+    html
+}
+
+fn month(html: &mut String, year: i32, month: u32, days: &BTreeMap<u32, Vec<&Capture>>) {
+    let Some(first) = NaiveDate::from_ymd_opt(year, month, 1) else {
+        return;
+    };
+    let last_day = first
+        .checked_add_months(Months::new(1))
+        .and_then(|d| d.pred_opt())
+        .map_or(31, |d| d.day());
+
+    let _ = write!(html, "<table><caption>{}</caption><tr>", first.format("%B"));
+    for weekday in WEEKDAYS {
+        let _ = write!(html, "<th>{weekday}</th>");
+    }
+    html.push_str("</tr>\n<tr>");
+    let offset = first.weekday().num_days_from_monday();
+    for _ in 0..offset {
+        html.push_str("<td></td>");
+    }
+    for day in 1..=last_day {
+        if day > 1 && (offset + day - 1) % 7 == 0 {
+            html.push_str("</tr>\n<tr>");
+        }
+        match days.get(&day).and_then(|c| Some((c.first()?, c))) {
+            Some((first, captures)) => {
+                let times: Vec<String> = captures
+                    .iter()
+                    .map(|c| format!("{} ({})", time(&c.timestamp), c.status))
+                    .collect();
+                let _ = write!(
+                    html,
+                    r#"<td><a href="{PREFIX}{}/{}" title="{}">{day}</a></td>"#,
+                    first.timestamp,
+                    escape(&first.url),
+                    times.join(", ")
+                );
+            }
+            None => {
+                let _ = write!(html, "<td>{day}</td>");
+            }
+        }
+    }
+    html.push_str("</tr></table>\n");
+}
+
+fn date(timestamp: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(timestamp.get(..8)?, "%Y%m%d").ok()
+}
+
+/// `20261018120000` as `12:00:00`
+fn time(timestamp: &str) -> String {
+    let t = timestamp.get(8..14).unwrap_or_default();
+    format!(
+        "{}:{}:{}",
+        t.get(..2).unwrap_or_default(),
+        t.get(2..4).unwrap_or_default(),
+        t.get(4..).unwrap_or_default()
+    )
+}
+
+/// `20261018120000` as `2026-10-18 12:00:00`
+fn display(timestamp: &str) -> String {
+    date(timestamp).map_or_else(
+        || timestamp.to_string(),
+        |d| format!("{} {}", d.format("%Y-%m-%d"), time(timestamp)),
+    )
+}
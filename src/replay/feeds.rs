@@ -0,0 +1,294 @@
+//! Atom feeds of the items of the subscribed feeds:
+//!
+//! - `/feeds/`: list of the feeds by category
+//! - `/feeds/atom`: newest items of all feeds
+//! - `/feeds/atom?category=Tech`: newest items of the feeds in `Tech` and
+//!   its subcategories
+//!
+//! Entries link to the live url of the item and, if the item has been
+//! archived, to the capture closest to the time the item was fetched.
+
+use super::{add_header, closest, escape, page, replay_url, Response};
+use crate::db::{self, models::FeedEntry};
+use crate::feed_reader::opml::split_category;
+use crate::warc::cdxj;
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use url::{form_urlencoded, Url};
+
+pub const FEEDS_PREFIX: &str = "/feeds/";
+const ATOM_TYPE: &str = "application/atom+xml";
+/// Number of entries in a feed
+const MAX_ENTRIES: i64 = 50;
+
+/// `target` is the request path without [`FEEDS_PREFIX`]
+pub fn serve(target: &str, origin: &str) -> Result<Response> {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let category = form_urlencoded::parse(query.as_bytes())
+        .find(|(k, _)| k == "category")
+        .map(|(_, v)| v.trim_matches('/').to_string())
+        .filter(|c| !c.is_empty());
+    let conn = &mut db::init_conn()?;
+    match path {
+        "" => Ok(page(
+            200,
+            "Feeds",
+            &index(&db::select_feed_categories(conn)?),
+        )),
+        "atom" => {
+            let entries = db::select_feed_entries(conn, category.as_deref(), MAX_ENTRIES)?;
+            let entries: Vec<_> = archived(&entries)?.into_iter().zip(entries).collect();
+            let body = atom(&entries, category.as_deref(), origin, Utc::now());
+            let mut response = Response::from_data(body.into_bytes());
+            add_header(
+                &mut response,
+                "content-type",
+                &format!("{ATOM_TYPE}; charset=utf-8"),
+            );
+            Ok(response)
+        }
+        _ => Ok(page(404, "Not found", "<p>No such page.</p>")),
+    }
+}
+
+/// Replay urls of the captures of the items closest to their fetch time,
+/// looked up in one pass over the index
+fn archived(entries: &[FeedEntry]) -> Result<Vec<Option<String>>> {
+    let links: Vec<Option<Url>> = entries
+        .iter()
+        .map(|e| e.link.as_deref().and_then(|l| Url::parse(l).ok()))
+        .collect();
+    let urls: Vec<&Url> = links.iter().flatten().collect();
+    let mut captures = cdxj::captures_of(&urls)?.into_iter();
+    Ok(entries
+        .iter()
+        .zip(&links)
+        .map(|(entry, link)| {
+            link.as_ref()?;
+            let captures = captures.next()?;
+            let timestamp = cdxj::timestamp(entry.fetched.into());
+            closest(&captures, &timestamp).map(|c| replay_url(&c.timestamp, &c.url))
+        })
+        .collect())
+}
+
+/// Links to the feed of all items and of each category with its parents
+fn index(categories: &[String]) -> String {
+    let all: BTreeMap<&str, (usize, String)> = categories
+        .iter()
+        .flat_map(|category| {
+            split_category(category)
+                .into_iter()
+                .enumerate()
+                .map(|(depth, (path, name))| (path, (depth, name)))
+        })
+        .collect();
+    let mut html = format!(
+        "<ul>\n<li><a href=\"{}\">All feeds</a></li>\n",
+        escape(&feed_path(None))
+    );
+    for (category, (depth, name)) in all {
+        let _ = writeln!(
+            html,
+            "<li style=\"margin-left: {depth}em\"><a href=\"{}\">{}</a></li>",
+            escape(&feed_path(Some(category))),
+            escape(&name)
+        );
+    }
+    html.push_str("</ul>");
+    html
+}
+
+/// Path of the Atom feed of the category or of all feeds
+fn feed_path(category: Option<&str>) -> String {
+    match category {
+        None => format!("{FEEDS_PREFIX}atom"),
+        Some(c) => format!(
+            "{FEEDS_PREFIX}atom?{}",
+            form_urlencoded::Serializer::new(String::new())
+                .append_pair("category", c)
+                .finish()
+        ),
+    }
+}
+
+/// Atom feed of the entries with the replay urls of their captures
+fn atom(
+    entries: &[(Option<String>, FeedEntry)],
+    category: Option<&str>,
+    origin: &str,
+    now: DateTime<Utc>,
+) -> String {
+    let self_url = format!("{origin}{}", feed_path(category));
+    let title = category.map_or_else(|| String::from("All feeds"), |c| format!("Feeds: {c}"));
+    let updated = entries.iter().map(|(_, e)| updated(e)).max().unwrap_or(now);
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <feed xmlns=\"http://www.w3.org/2005/Atom\">\n\
+         \x20 <id>{0}</id>\n\
+         \x20 <title>{1}</title>\n\
+         \x20 <updated>{2}</updated>\n\
+         \x20 <link rel=\"self\" type=\"{ATOM_TYPE}\" href=\"{0}\"/>\n\
+         \x20 <generator>Lara</generator>\n",
+        escape(&self_url),
+        escape(&title),
+        rfc3339(updated),
+    );
+    for (archived, e) in entries {
+        let archived = archived.as_ref().map(|path| format!("{origin}{path}"));
+        entry(&mut xml, e, archived.as_deref());
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+fn entry(xml: &mut String, e: &FeedEntry, archived: Option<&str>) {
+    // Ids must be IRIs, the ids of RSS items need not be
+    let id = match Url::parse(&e.guid) {
+        Ok(_) => e.guid.clone(),
+        Err(_) => format!("urn:lara:feed-item:{}", e.feed_item_id),
+    };
+    let title = e
+        .title
+        .as_deref()
+        .or(e.link.as_deref())
+        .unwrap_or("Untitled");
+    let feed_title = e.feed_title.as_deref().unwrap_or(&e.feed_url);
+    let _ = write!(
+        xml,
+        "  <entry>\n    <id>{}</id>\n    <title>{}</title>\n",
+        escape(&id),
+        escape(title)
+    );
+    let mut links = Vec::new();
+    if let Some(link) = &e.link {
+        let _ = writeln!(
+            xml,
+            "    <link rel=\"alternate\" href=\"{}\"/>",
+            escape(link)
+        );
+        links.push(format!("<a href=\"{}\">Original</a>", escape(link)));
+    }
+    if let Some(archived) = archived {
+        let _ = writeln!(
+            xml,
+            "    <link rel=\"related\" title=\"Archived capture\" href=\"{}\"/>",
+            escape(archived)
+        );
+        links.push(format!(
+            "<a href=\"{}\">Archived capture</a>",
+            escape(archived)
+        ));
+    }
+    if let Some(published) = e.published {
+        let _ = writeln!(xml, "    <published>{}</published>", rfc3339(published));
+    }
+    let _ = write!(
+        xml,
+        "    <updated>{}</updated>\n    <author><name>{}</name></author>\n",
+        rfc3339(updated(e)),
+        escape(e.author.as_deref().unwrap_or(feed_title))
+    );
+    let _ = write!(
+        xml,
+        "    <source>\n      <id>{0}</id>\n      <title>{1}</title>\n      \
+         <link rel=\"self\" href=\"{0}\"/>\n    </source>\n",
+        escape(&e.feed_url),
+        escape(feed_title)
+    );
+    let mut content = e.content.clone().unwrap_or_default();
+    if !links.is_empty() {
+        if !content.is_empty() {
+            content.push('\n');
+        }
+        let _ = write!(content, "<p>{}</p>", links.join(" | "));
+    }
+    let _ = write!(
+        xml,
+        "    <content type=\"html\">{}</content>\n  </entry>\n",
+        escape(&content)
+    );
+}
+
+/// Atom requires an update time for each entry
+fn updated(e: &FeedEntry) -> DateTime<Utc> {
+    e.updated.or(e.published).unwrap_or(e.fetched)
+}
+
+fn rfc3339(t: DateTime<Utc>) -> String {
+    t.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{atom, index};
+    use crate::db::models::FeedEntry;
+    use chrono::{DateTime, Utc};
+
+    fn entry(guid: &str) -> FeedEntry {
+        FeedEntry {
+            feed_item_id: 7,
+            guid: guid.to_string(),
+            link: Some(String::from("https://example.org/post?a=1&b=2")),
+            title: Some(String::from("Post <1>")),
+            author: None,
+            published: Some("2026-10-17T12:00:00Z".parse().unwrap()),
+            updated: None,
+            content: Some(String::from("<p>Hello</p>")),
+            fetched: "2026-10-18T08:00:00Z".parse().unwrap(),
+            feed_url: String::from("https://example.org/feed.xml"),
+            feed_title: Some(String::from("Example")),
+        }
+    }
+
+    #[test]
+    fn atom_entries() {
+        let now: DateTime<Utc> = "2026-10-18T12:00:00Z".parse().unwrap();
+        let entries = [
+            (
+                Some(String::from(
+                    "/web/20261018080000/https://example.org/post?a=1&b=2",
+                )),
+                entry("https://example.org/post?a=1&b=2"),
+            ),
+            (None, entry("42")),
+        ];
+        let xml = atom(&entries, Some("Tech/Rust"), "http://a", now);
+        assert!(xml.contains(
+            r#"<link rel="self" type="application/atom+xml" href="http://a/feeds/atom?category=Tech%2FRust"/>"#
+        ));
+        assert!(xml.contains("<updated>2026-10-17T12:00:00Z</updated>\n  <link"));
+        assert!(xml.contains("<id>https://example.org/post?a=1&amp;b=2</id>"));
+        assert!(xml.contains("<id>urn:lara:feed-item:7</id>"));
+        assert!(xml.contains("<title>Post &lt;1&gt;</title>"));
+        assert!(xml.contains(r#"<link rel="related" title="Archived capture" href="http://a/web/20261018080000/https://example.org/post?a=1&amp;b=2"/>"#));
+        assert!(xml.contains("<author><name>Example</name></author>"));
+        assert!(xml.contains(
+            "&lt;p&gt;Hello&lt;/p&gt;\n&lt;p&gt;&lt;a href=&quot;https://example.org/post?a=1&amp;amp;b=2&quot;&gt;Original&lt;/a&gt; | "
+        ));
+        assert_eq!(xml.matches("<entry>").count(), 2);
+        assert_eq!(xml.matches("Archived capture").count(), 2);
+
+        let empty = atom(&[], None, "http://a", now);
+        assert!(empty.contains("<updated>2026-10-18T12:00:00Z</updated>"));
+    }
+
+    #[test]
+    fn category_index() {
+        let html = index(&[
+            String::from(r"Music/AC\/DC"),
+            String::from("News"),
+            String::from("Tech/Rust"),
+        ]);
+        assert!(html.contains(r#"<a href="/feeds/atom?category=Tech">Tech</a>"#));
+        assert!(html.contains(
+            r#"<li style="margin-left: 1em"><a href="/feeds/atom?category=Tech%2FRust">Rust</a>"#
+        ));
+        assert!(html.contains(
+            r#"<li style="margin-left: 1em"><a href="/feeds/atom?category=Music%2FAC%5C%2FDC">AC/DC</a>"#
+        ));
+        assert_eq!(html.matches("<li").count(), 6);
+    }
+}
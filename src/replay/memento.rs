@@ -0,0 +1,214 @@
+//! Memento framework, RFC 7089 <https://www.rfc-editor.org/rfc/rfc7089>:
+//!
+//! - Time gate `/timegate/{url}` and `/web/{url}`: redirects to the capture
+//!   closest to the `Accept-Datetime` header, by default to the latest
+//! - Time maps `/timemap/link/{url}` in link-format (RFC 6690) and
+//!   `/timemap/json/{url}` listing all captures of the url
+//! - `Memento-Datetime` and `Link` headers on replayed captures
+
+use super::{
+    add_header, closest, not_archived, page, parse_timestamp, parse_url, redirect, replay_url,
+    Response,
+};
+use crate::warc::cdxj::{self, Capture};
+use anyhow::Result;
+use serde_json::json;
+use std::time::SystemTime;
+use url::Url;
+
+pub const TIMEGATE_PREFIX: &str = "/timegate/";
+pub const TIMEMAP_PREFIX: &str = "/timemap/";
+const LINK_FORMAT: &str = "application/link-format";
+
+/// Redirects to the capture closest to `accept_datetime`, an HTTP date
+pub fn timegate(url: &str, accept_datetime: Option<&str>, origin: &str) -> Result<Response> {
+    let Some(url) = parse_url(url) else {
+        return Ok(page(404, "Not found", "<p>Not a valid url.</p>"));
+    };
+    let datetime = match accept_datetime.map(httpdate::parse_http_date) {
+        None => SystemTime::now(),
+        Some(Ok(datetime)) => datetime,
+        Some(Err(_)) => {
+            return Ok(page(
+                400,
+                "Bad request",
+                "<p>Invalid Accept-Datetime header.</p>",
+            ))
+        }
+    };
+    let captures = cdxj::captures(&url)?;
+    let Some(capture) = closest(&captures, &cdxj::timestamp(datetime)) else {
+        return Ok(not_archived(&url));
+    };
+    let mut response = redirect(&format!(
+        "{origin}{}",
+        replay_url(&capture.timestamp, &capture.url)
+    ));
+    let links = [
+        format!("<{}>; rel=\"original\"", capture.url),
+        timemap_link(origin, &capture.url),
+    ];
+    add_header(&mut response, "vary", "accept-datetime");
+    add_header(&mut response, "link", &links.join(", "));
+    Ok(response)
+}
+
+/// `/timemap/link/{url}` or `/timemap/json/{url}`
+pub fn timemap(target: &str, origin: &str) -> Result<Response> {
+    let (format, url) = target.split_once('/').unwrap_or((target, ""));
+    let Some(url) = parse_url(url) else {
+        return Ok(page(404, "Not found", "<p>Not a valid url.</p>"));
+    };
+    let captures = cdxj::captures(&url)?;
+    if captures.is_empty() {
+        return Ok(not_archived(&url));
+    }
+    let (body, content_type) = match format {
+        "link" => (link_format(&url, &captures, origin), LINK_FORMAT),
+        "json" => (json_format(&url, &captures, origin), "application/json"),
+        _ => return Ok(page(404, "Not found", "<p>No such TimeMap format.</p>")),
+    };
+    let mut response = Response::from_data(body.into_bytes());
+    add_header(&mut response, "content-type", content_type);
+    Ok(response)
+}
+
+/// Adds the `Memento-Datetime` and `Link` headers to a replayed capture
+pub fn add_headers(response: &mut Response, capture: &Capture, captures: &[Capture], origin: &str) {
+    let mut links = vec![
+        format!("<{}>; rel=\"original\"", capture.url),
+        format!(
+            "<{origin}{TIMEGATE_PREFIX}{}>; rel=\"timegate\"",
+            capture.url
+        ),
+        timemap_link(origin, &capture.url),
+    ];
+    let position = captures
+        .iter()
+        .position(|c| c.timestamp == capture.timestamp);
+    let neighbours = [
+        ("first", captures.first()),
+        (
+            "prev",
+            position
+                .and_then(|p| p.checked_sub(1))
+                .and_then(|p| captures.get(p)),
+        ),
+        ("next", position.and_then(|p| captures.get(p + 1))),
+        ("last", captures.last()),
+    ];
+    for (rel, neighbour) in neighbours {
+        match neighbour {
+            Some(c) if c.timestamp != capture.timestamp => {
+                links.push(memento_link(&format!("{rel} memento"), c, origin));
+            }
+            _ => (),
+        }
+    }
+    if let Some(datetime) = http_date(&capture.timestamp) {
+        add_header(response, "memento-datetime", &datetime);
+    }
+    add_header(response, "link", &links.join(", "));
+}
+
+fn link_format(url: &Url, captures: &[Capture], origin: &str) -> String {
+    let mut links = vec![
+        format!("<{url}>; rel=\"original\""),
+        format!(
+            "<{origin}{TIMEMAP_PREFIX}link/{url}>; rel=\"self\"; type=\"{LINK_FORMAT}\"{}{}",
+            captures
+                .first()
+                .and_then(|c| http_date(&c.timestamp))
+                .map(|d| format!("; from=\"{d}\""))
+                .unwrap_or_default(),
+            captures
+                .last()
+                .and_then(|c| http_date(&c.timestamp))
+                .map(|d| format!("; until=\"{d}\""))
+                .unwrap_or_default(),
+        ),
+        format!("<{origin}{TIMEGATE_PREFIX}{url}>; rel=\"timegate\""),
+    ];
+    let last = captures.len() - 1;
+    for (i, capture) in captures.iter().enumerate() {
+        let rel = match (i == 0, i == last) {
+            (true, true) => "first last memento",
+            (true, false) => "first memento",
+            (false, true) => "last memento",
+            (false, false) => "memento",
+        };
+        links.push(memento_link(rel, capture, origin));
+    }
+    links.join(",\n") + "\n"
+}
+
+fn json_format(url: &Url, captures: &[Capture], origin: &str) -> String {
+    let memento = |c: &Capture| {
+        json!({
+            "datetime": parse_timestamp(&c.timestamp).map(|d| d.to_rfc3339()),
+            "uri": format!("{origin}{}", replay_url(&c.timestamp, &c.url)),
+        })
+    };
+    json!({
+        "original_uri": url.as_str(),
+        "timegate_uri": format!("{origin}{TIMEGATE_PREFIX}{url}"),
+        "timemap_uri": {
+            "link_format": format!("{origin}{TIMEMAP_PREFIX}link/{url}"),
+            "json_format": format!("{origin}{TIMEMAP_PREFIX}json/{url}"),
+        },
+        "mementos": {
+            "first": captures.first().map(memento),
+            "last": captures.last().map(memento),
+            "list": captures.iter().map(memento).collect::<Vec<_>>(),
+        },
+    })
+    .to_string()
+}
+
+fn memento_link(rel: &str, capture: &Capture, origin: &str) -> String {
+    format!(
+        "<{origin}{}>; rel=\"{rel}\"; datetime=\"{}\"",
+        replay_url(&capture.timestamp, &capture.url),
+        http_date(&capture.timestamp).unwrap_or_default()
+    )
+}
+
+fn timemap_link(origin: &str, url: &str) -> String {
+    format!("<{origin}{TIMEMAP_PREFIX}link/{url}>; rel=\"timemap\"; type=\"{LINK_FORMAT}\"")
+}
+
+/// `20261018120000` as `Sun, 18 Oct 2026 12:00:00 GMT`
+fn http_date(timestamp: &str) -> Option<String> {
+    parse_timestamp(timestamp).map(|d| httpdate::fmt_http_date(d.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{http_date, link_format};
+    use crate::replay::tests::capture;
+    use url::Url;
+
+    #[test]
+    fn timemap_link_format() {
+        let url = Url::parse("https://example.org/").unwrap();
+        let captures = [capture("20251018120000"), capture("20261018120000")];
+        assert_eq!(
+            link_format(&url, &captures, "http://a"),
+            r#"<https://example.org/>; rel="original",
+<http://a/timemap/link/https://example.org/>; rel="self"; type="application/link-format"; from="Sat, 18 Oct 2025 12:00:00 GMT"; until="Sun, 18 Oct 2026 12:00:00 GMT",
+<http://a/timegate/https://example.org/>; rel="timegate",
+<http://a/web/20251018120000/https://example.org/>; rel="first memento"; datetime="Sat, 18 Oct 2025 12:00:00 GMT",
+<http://a/web/20261018120000/https://example.org/>; rel="last memento"; datetime="Sun, 18 Oct 2026 12:00:00 GMT"
+"#
+        );
+        assert!(link_format(&url, &captures[..1], "").contains(r#"rel="first last memento""#));
+    }
+
+    #[test]
+    fn http_dates() {
+        assert_eq!(
+            http_date("20261018120000").as_deref(),
+            Some("Sun, 18 Oct 2026 12:00:00 GMT")
+        );
+    }
+}
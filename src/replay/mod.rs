@@ -0,0 +1,413 @@
+//! Wayback-style replay of the archive over HTTP:
+//!
+//! - `/web/{timestamp}/{url}`: the capture of the url closest to the
+//!   timestamp, with links rewritten to stay inside the archive. Redirects to
+//!   the timestamp of the capture if it differs from the requested one. The
+//!   timestamp may be shortened, e.g. to the year.
+//! - `/web/{timestamp}id_/{url}`: the same without rewritten links
+//! - `/web/{url}`: the latest capture, or the one closest to the
+//!   `Accept-Datetime` header
+//! - `/web/*/{url}`: calendar of the captures of the url
+//! - Memento time gate and time maps, see [`memento`]
+//! - Atom feeds of the items of the subscribed feeds, see [`feeds`]
+//!
+//! Captures are looked up in the CDXJ index files in `ARCHIVE_DIR`.
+
+use crate::env_config::ARCHIVE_DIR;
+use crate::url_util::is_http_s;
+use crate::warc::cdxj::{self, Capture};
+use crate::warc::reader::{self, HttpResponse};
+use crate::warc::Record;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use tiny_http::{Header, Request, Server};
+use url::{form_urlencoded, Url};
+
+mod calendar;
+mod feeds;
+mod memento;
+mod rewrite;
+
+const DEFAULT_ADDR: &str = "127.0.0.1:8080";
+/// Number of threads answering requests
+const THREADS: usize = 4;
+/// Path prefix of replayed captures
+const PREFIX: &str = "/web/";
+/// Flag after the timestamp to serve a capture without rewriting
+const RAW_FLAG: &str = "id_";
+
+type Response = tiny_http::Response<Cursor<Vec<u8>>>;
+
+/// Command `replay [ADDR]`: Serves the archive on the address, by default
+/// on `127.0.0.1:8080`.
+pub fn serve(args: &[String]) -> Result<()> {
+    let addr = args.first().map_or(DEFAULT_ADDR, String::as_str);
+    let server = Arc::new(Server::http(addr).map_err(|e| anyhow!("{addr}: {e}"))?);
+    info!("Replaying the archive on http://{addr}/");
+
+    let mut threads = Vec::new();
+    for i in 0..THREADS {
+        let server = Arc::clone(&server);
+        threads.push(
+            thread::Builder::new()
+                .name(format!("replay-{i}"))
+                .spawn(move || {
+                    for request in server.incoming_requests() {
+                        handle(request);
+                    }
+                })?,
+        );
+    }
+    for thread in threads {
+        thread
+            .join()
+            .map_err(|_| anyhow!("Replay thread panicked"))?;
+    }
+    Ok(())
+}
+
+fn handle(request: Request) {
+    let response = route(&request).unwrap_or_else(|e| {
+        error!("{}: {e:#}", request.url());
+        page(
+            500,
+            "Error",
+            &format!("<p>{}</p>", escape(&format!("{e:#}"))),
+        )
+    });
+    if let Err(e) = request.respond(response) {
+        debug!("Could not send response: {e}");
+    }
+}
+
+fn route(request: &Request) -> Result<Response> {
+    let path = request.url();
+    // Links in Memento headers and TimeMaps are absolute
+    let origin = header(request, "host").map_or_else(String::new, |h| format!("http://{h}"));
+    if let Some(target) = path.strip_prefix(PREFIX) {
+        return replay(target, request, &origin);
+    }
+    if let Some(url) = path.strip_prefix(memento::TIMEGATE_PREFIX) {
+        return memento::timegate(url, header(request, "accept-datetime"), &origin);
+    }
+    if let Some(target) = path.strip_prefix(memento::TIMEMAP_PREFIX) {
+        return memento::timemap(target, &origin);
+    }
+    if let Some(target) = path.strip_prefix(feeds::FEEDS_PREFIX) {
+        return feeds::serve(target, &origin);
+    }
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+    if path != "/" {
+        return Ok(page(404, "Not found", "<p>No such page.</p>"));
+    }
+    let url = form_urlencoded::parse(query.as_bytes()).find(|(k, _)| k == "url");
+    Ok(match url {
+        Some((_, url)) if !url.is_empty() => redirect(&format!("{PREFIX}*/{url}")),
+        _ => page(
+            200,
+            "Lara archive",
+            r#"<form action="/"><input name="url" size="60" placeholder="https://example.org/" autofocus> <button>Search</button></form>"#,
+        ),
+    })
+}
+
+fn replay(target: &str, request: &Request, origin: &str) -> Result<Response> {
+    let (timestamp, url) = match target.split_once('/') {
+        Some(("*", url)) => return calendar(url),
+        Some((ts, url)) if is_timestamp(ts.strip_suffix(RAW_FLAG).unwrap_or(ts)) => (ts, url),
+        _ => return memento::timegate(target, header(request, "accept-datetime"), origin),
+    };
+    let raw = timestamp.ends_with(RAW_FLAG);
+    let timestamp = timestamp.strip_suffix(RAW_FLAG).unwrap_or(timestamp);
+    let Some(url) = parse_url(url) else {
+        return Ok(page(404, "Not found", "<p>Not a valid url.</p>"));
+    };
+
+    let captures = cdxj::captures(&url)?;
+    let Some(capture) = closest(&captures, timestamp) else {
+        return Ok(not_archived(&url));
+    };
+    if timestamp != capture.timestamp {
+        let flag = if raw { RAW_FLAG } else { "" };
+        return Ok(redirect(&format!(
+            "{PREFIX}{}{flag}/{}",
+            capture.timestamp, capture.url
+        )));
+    }
+    let mut response = serve_capture(capture, &captures, raw)?;
+    memento::add_headers(&mut response, capture, &captures, origin);
+    Ok(response)
+}
+
+fn serve_capture(capture: &Capture, captures: &[Capture], raw: bool) -> Result<Response> {
+    let dir = ARCHIVE_DIR.parse::<PathBuf>();
+    let record = reader::read_at(&dir, &capture.location)?;
+    let original = if capture.is_revisit() {
+        match original(&record, capture, captures)? {
+            Some(original) => Some(original),
+            None => {
+                return Ok(page(
+                    404,
+                    "Not found",
+                    "<p>The payload of this capture is not archived.</p>",
+                ))
+            }
+        }
+    } else {
+        None
+    };
+
+    let http = HttpResponse::parse(&record.block)?;
+    let original_http = original
+        .as_ref()
+        .map(|o| HttpResponse::parse(&o.block))
+        .transpose()?;
+    let http = match original_http {
+        // The revisit of a 304 response has only the headers of the 304
+        Some(original_http) if http.status == 304 => original_http,
+        Some(original_http) => HttpResponse {
+            body: original_http.body,
+            ..http
+        },
+        None => http,
+    };
+
+    let base = Url::parse(&capture.url)?;
+    let body = if raw {
+        http.body.to_vec()
+    } else {
+        rewrite::body(http.mime(), http.body, &base, &capture.timestamp)
+    };
+    let mut response = Response::from_data(body).with_status_code(http.status);
+    for (name, value) in &http.headers {
+        let name = name.to_ascii_lowercase();
+        let header = match name.as_str() {
+            // The length of the rewritten body is set by the server
+            "content-encoding" | "content-length" | "transfer-encoding" | "connection"
+            | "keep-alive" => continue,
+            // Renamed when archived
+            _ if name.starts_with("x-archive-orig-") => Header::from_bytes(name, *value),
+            "content-type" => Header::from_bytes(name, *value),
+            "location" => {
+                let location = base.join(value).map_or_else(
+                    |_| (*value).to_string(),
+                    |l| replay_url(&capture.timestamp, l.as_str()),
+                );
+                Header::from_bytes(name, location)
+            }
+            _ => Header::from_bytes(format!("x-archive-orig-{name}"), *value),
+        };
+        if let Ok(header) = header {
+            response.add_header(header);
+        }
+    }
+    Ok(response)
+}
+
+/// The response record holding the payload of a revisit record
+fn original(revisit: &Record, capture: &Capture, captures: &[Capture]) -> Result<Option<Record>> {
+    let found = match (
+        revisit.get("WARC-Refers-To-Target-URI"),
+        revisit.get("WARC-Refers-To-Date"),
+    ) {
+        (Some(uri), Some(date)) => {
+            let timestamp = cdxj::timestamp(DateTime::parse_from_rfc3339(date)?.into());
+            cdxj::captures(&Url::parse(uri)?)?
+                .into_iter()
+                .find(|c| c.timestamp == timestamp && !c.is_revisit())
+        }
+        // Revisit of a 304 response: the latest response before
+        _ => captures
+            .iter()
+            .rev()
+            .find(|c| c.timestamp < capture.timestamp && !c.is_revisit())
+            .cloned(),
+    };
+    let dir = ARCHIVE_DIR.parse::<PathBuf>();
+    found
+        .map(|c| reader::read_at(&dir, &c.location))
+        .transpose()
+}
+
+fn calendar(url: &str) -> Result<Response> {
+    let Some(url) = parse_url(url) else {
+        return Ok(page(404, "Not found", "<p>Not a valid url.</p>"));
+    };
+    let captures = cdxj::captures(&url)?;
+    if captures.is_empty() {
+        return Ok(not_archived(&url));
+    }
+    Ok(page(
+        200,
+        &format!("Captures of {url}"),
+        &calendar::html(&captures),
+    ))
+}
+
+fn not_archived(url: &Url) -> Response {
+    page(
+        404,
+        "Not archived",
+        &format!(
+            r#"<p><a href="{0}">{0}</a> is not archived.</p>"#,
+            escape(url.as_str())
+        ),
+    )
+}
+
+/// Url of the capture with the timestamp in the archive
+fn replay_url(timestamp: &str, url: &str) -> String {
+    format!("{PREFIX}{timestamp}/{url}")
+}
+
+/// Parses the url, assuming http for urls without scheme
+fn parse_url(url: &str) -> Option<Url> {
+    match Url::parse(url) {
+        Ok(url) if is_http_s(&url) => Some(url),
+        _ => Url::parse(&format!("http://{url}"))
+            .ok()
+            .filter(|u| u.host_str().is_some_and(|h| h.contains('.'))),
+    }
+}
+
+fn is_timestamp(s: &str) -> bool {
+    (1..=14).contains(&s.len()) && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Parses a 14 digit timestamp. Shortened timestamps denote the start of
+/// the period, e.g. `2026` the start of the year and `20261` the start of
+/// October, the first month starting with 1. Fields out of range are
+/// clamped, e.g. `20260231` is the 28th of February.
+fn parse_timestamp(timestamp: &str) -> Option<DateTime<Utc>> {
+    if !is_timestamp(timestamp) {
+        return None;
+    }
+    let padded = format!("{timestamp:0<14}");
+    let field = |start: usize| padded[start..start + 2].parse::<u32>().ok();
+    let year = padded[..4].parse().ok()?;
+    let month = field(4)?.clamp(1, 12);
+    let day = field(6)?.clamp(1, 31);
+    let date = (28..=day)
+        .rev()
+        .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+        .or_else(|| NaiveDate::from_ymd_opt(year, month, day))?;
+    date.and_hms_opt(field(8)?.min(23), field(10)?.min(59), field(12)?.min(59))
+        .map(|dt| dt.and_utc())
+}
+
+/// The capture closest in time to the timestamp
+fn closest<'a>(captures: &'a [Capture], timestamp: &str) -> Option<&'a Capture> {
+    let target = parse_timestamp(timestamp)?;
+    captures.iter().min_by_key(|c| {
+        parse_timestamp(&c.timestamp).map_or(i64::MAX, |t| (t - target).num_seconds().abs())
+    })
+}
+
+/// Value of the request header
+fn header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str())
+}
+
+fn redirect(location: &str) -> Response {
+    let mut response = Response::from_data(Vec::new()).with_status_code(302);
+    add_header(&mut response, "location", location);
+    response
+}
+
+/// Adds the header unless it is invalid
+fn add_header(response: &mut Response, name: &str, value: &str) {
+    if let Ok(header) = Header::from_bytes(name, value) {
+        response.add_header(header);
+    }
+}
+
+/// HTML page of the replay server itself
+fn page(status: u16, title: &str, body: &str) -> Response {
+    let title = escape(title);
+    let html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title>\
+         <style>{}</style></head>\n<body><h1>{title}</h1>\n{body}\n</body></html>\n",
+        calendar::STYLE
+    );
+    let mut response = Response::from_data(html.into_bytes()).with_status_code(status);
+    add_header(&mut response, "content-type", "text/html; charset=utf-8");
+    response
+}
+
+/// Escapes text for HTML content and attribute values
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{closest, is_timestamp, parse_timestamp, parse_url};
+    use crate::warc::cdxj::Capture;
+    use crate::warc::WarcLocation;
+
+    pub fn capture(timestamp: &str) -> Capture {
+        Capture {
+            timestamp: timestamp.to_string(),
+            url: "https://example.org/".to_string(),
+            mime: "text/html".to_string(),
+            status: "200".to_string(),
+            location: WarcLocation {
+                filename: String::new(),
+                offset: 0,
+                length: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn closest_capture() {
+        let captures = [capture("20250101000000"), capture("20260601000000")];
+        let ts = |t| closest(&captures, t).unwrap().timestamp.as_str();
+        assert_eq!(ts("2025"), "20250101000000");
+        assert_eq!(ts("20260101"), "20260601000000");
+        assert_eq!(ts("20990101000000"), "20260601000000");
+        assert!(closest(&[], "2026").is_none());
+    }
+
+    #[test]
+    fn partial_timestamps() {
+        let ts = |t| parse_timestamp(t).map(|d| d.format("%Y%m%d%H%M%S").to_string());
+        assert_eq!(ts("2026").unwrap(), "20260101000000");
+        assert_eq!(ts("20261").unwrap(), "20261001000000");
+        assert_eq!(ts("202603").unwrap(), "20260301000000");
+        assert_eq!(ts("2026033").unwrap(), "20260330000000");
+        assert_eq!(ts("202610181").unwrap(), "20261018100000");
+        assert_eq!(ts("20262").unwrap(), "20261201000000");
+        assert_eq!(ts("20260231").unwrap(), "20260228000000");
+        assert_eq!(ts("20240231").unwrap(), "20240229000000");
+        assert_eq!(ts("20261018259999").unwrap(), "20261018235959");
+        assert!(ts("2026-").is_none());
+    }
+
+    #[test]
+    fn timestamps_and_urls() {
+        assert!(is_timestamp("2026"));
+        assert!(!is_timestamp("https:"));
+        assert!(!is_timestamp("202610181200001"));
+        assert_eq!(
+            parse_url("example.org/a?b").unwrap().as_str(),
+            "http://example.org/a?b"
+        );
+        assert_eq!(
+            parse_url("https://example.org").unwrap().as_str(),
+            "https://example.org/"
+        );
+        assert!(parse_url("*").is_none());
+    }
+}
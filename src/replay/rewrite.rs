@@ -0,0 +1,212 @@
+//! Rewrites links in archived HTML and CSS to point into the archive.
+//! Links created by scripts are not rewritten.
+
+use crate::url_util::is_http_s;
+use anyhow::Result;
+use lol_html::html_content::ContentType;
+use lol_html::{element, text, HtmlRewriter, Settings};
+use std::cell::RefCell;
+use url::Url;
+
+/// Elements and their attributes holding a single link
+const LINK_ATTRIBUTES: [(&str, &str); 17] = [
+    ("a[href]", "href"),
+    ("area[href]", "href"),
+    ("link[href]", "href"),
+    ("img[src]", "src"),
+    ("script[src]", "src"),
+    ("iframe[src]", "src"),
+    ("frame[src]", "src"),
+    ("embed[src]", "src"),
+    ("source[src]", "src"),
+    ("track[src]", "src"),
+    ("audio[src]", "src"),
+    ("video[src]", "src"),
+    ("video[poster]", "poster"),
+    ("input[src]", "src"),
+    ("form[action]", "action"),
+    ("object[data]", "data"),
+    ("blockquote[cite]", "cite"),
+];
+
+/// Rewrites the links in HTML and CSS bodies, returns other bodies unchanged
+pub fn body(mime: Option<&str>, body: &[u8], base: &Url, timestamp: &str) -> Vec<u8> {
+    match mime {
+        Some("text/html" | "application/xhtml+xml") => {
+            html(body, base, timestamp).unwrap_or_else(|e| {
+                debug!("Could not rewrite {base}: {e}");
+                body.to_vec()
+            })
+        }
+        Some("text/css") => match std::str::from_utf8(body) {
+            Ok(s) => css(s, base, timestamp).into_bytes(),
+            Err(_) => body.to_vec(),
+        },
+        _ => body.to_vec(),
+    }
+}
+
+/// Replay url of the link, None for links that are not http(s) or only a
+/// fragment
+fn url(link: &str, base: &Url, timestamp: &str) -> Option<String> {
+    let link = link.trim();
+    if link.is_empty() || link.starts_with('#') {
+        return None;
+    }
+    let url = base.join(link).ok()?;
+    is_http_s(&url).then(|| super::replay_url(timestamp, url.as_str()))
+}
+
+fn html(body: &[u8], base: &Url, timestamp: &str) -> Result<Vec<u8>> {
+    // Links are relative to <base href> if present, which is then removed
+    let base = RefCell::new(base.clone());
+    let mut handlers = vec![element!("base[href]", |el| {
+        let href = el.get_attribute("href").unwrap_or_default();
+        let joined = base.borrow().join(&href);
+        if let Ok(url) = joined {
+            *base.borrow_mut() = url;
+        }
+        el.remove();
+        Ok(())
+    })];
+    for (selector, attribute) in LINK_ATTRIBUTES {
+        let base = &base;
+        handlers.push(element!(selector, move |el| {
+            let link = el.get_attribute(attribute).unwrap_or_default();
+            if let Some(url) = url(&link, &base.borrow(), timestamp) {
+                el.set_attribute(attribute, &url)?;
+            }
+            Ok(())
+        }));
+    }
+    handlers.push(element!("img[srcset], source[srcset]", |el| {
+        let value = el.get_attribute("srcset").unwrap_or_default();
+        el.set_attribute("srcset", &srcset(&value, &base.borrow(), timestamp))?;
+        Ok(())
+    }));
+    handlers.push(element!("[style]", |el| {
+        let value = el.get_attribute("style").unwrap_or_default();
+        el.set_attribute("style", &css(&value, &base.borrow(), timestamp))?;
+        Ok(())
+    }));
+    // The text of an element may come in several chunks
+    let mut style = String::new();
+    let style_base = &base;
+    handlers.push(text!("style", move |chunk| {
+        style.push_str(chunk.as_str());
+        if chunk.last_in_text_node() {
+            chunk.replace(
+                &css(&style, &style_base.borrow(), timestamp),
+                ContentType::Html,
+            );
+            style.clear();
+        } else {
+            chunk.remove();
+        }
+        Ok(())
+    }));
+
+    let mut output = Vec::with_capacity(body.len());
+    let settings = handlers
+        .into_iter()
+        .fold(Settings::new(), Settings::append_element_content_handler);
+    let mut rewriter = HtmlRewriter::new(settings, |chunk: &[u8]| {
+        output.extend_from_slice(chunk);
+    });
+    rewriter.write(body)?;
+    rewriter.end()?;
+    Ok(output)
+}
+
+/// Rewrites the urls of a `srcset` attribute: `a.png 1x, b.png 2x`
+fn srcset(value: &str, base: &Url, timestamp: &str) -> String {
+    value
+        .split(',')
+        .map(|candidate| {
+            let candidate = candidate.trim();
+            let (link, descriptor) = candidate
+                .split_once(char::is_whitespace)
+                .unwrap_or((candidate, ""));
+            let link = url(link, base, timestamp).unwrap_or_else(|| link.to_string());
+            match descriptor.trim() {
+                "" => link,
+                descriptor => format!("{link} {descriptor}"),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Rewrites the `url(...)` references in CSS
+fn css(css: &str, base: &Url, timestamp: &str) -> String {
+    let mut output = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("url(") {
+        let (before, after) = rest.split_at(start + "url(".len());
+        output.push_str(before);
+        let Some(end) = after.find(')') else {
+            rest = after;
+            break;
+        };
+        let link = after[..end].trim().trim_matches(['"', '\'']);
+        match url(link, base, timestamp) {
+            Some(url) => {
+                output.push('"');
+                output.push_str(&url);
+                output.push('"');
+            }
+            None => output.push_str(&after[..end]),
+        }
+        rest = &after[end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{body, css, srcset};
+    use url::Url;
+
+    const TS: &str = "20261018120000";
+
+    fn base() -> Url {
+        Url::parse("https://example.org/dir/page.html").unwrap()
+    }
+
+    #[test]
+    fn rewrite_html() {
+        let html = r##"<a href="other.html">x</a><a href="#top">t</a><a href="mailto:a@b.c">m</a><img src="/i.png" srcset="a.png 1x, b.png 2x"><form action="https://example.com/s"></form>"##;
+        let out = body(Some("text/html"), html.as_bytes(), &base(), TS);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r##"<a href="/web/20261018120000/https://example.org/dir/other.html">x</a><a href="#top">t</a><a href="mailto:a@b.c">m</a><img src="/web/20261018120000/https://example.org/i.png" srcset="/web/20261018120000/https://example.org/dir/a.png 1x, /web/20261018120000/https://example.org/dir/b.png 2x"><form action="/web/20261018120000/https://example.com/s"></form>"##
+        );
+    }
+
+    #[test]
+    fn rewrite_base_and_style() {
+        let html = r#"<head><base href="https://cdn.example.org/"><style>body { background: url(bg.png) }</style></head><p style="background: url('/p.png')"><a href="a">a</a></p>"#;
+        let out = body(Some("text/html"), html.as_bytes(), &base(), TS);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"<head><style>body { background: url("/web/20261018120000/https://cdn.example.org/bg.png") }</style></head><p style="background: url(&quot;/web/20261018120000/https://cdn.example.org/p.png&quot;)"><a href="/web/20261018120000/https://cdn.example.org/a">a</a></p>"#
+        );
+    }
+
+    #[test]
+    fn rewrite_css() {
+        assert_eq!(
+            css(
+                r#"a { b: url( "x.png" ) } c { d: url(data:image/png;base64,AA) }"#,
+                &base(),
+                TS
+            ),
+            r#"a { b: url("/web/20261018120000/https://example.org/dir/x.png") } c { d: url(data:image/png;base64,AA) }"#
+        );
+        assert_eq!(
+            srcset("a.png", &base(), TS),
+            "/web/20261018120000/https://example.org/dir/a.png"
+        );
+    }
+}
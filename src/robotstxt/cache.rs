@@ -1,7 +1,6 @@
 use crate::clock;
 
 use std::collections::HashMap;
-use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 
@@ -12,13 +11,13 @@ pub enum AccessResult<T> {
     Unavailable,
     /// HTTP 500-599 range
     Unreachable(SystemTime),
-    Ok(Rc<T>),
+    Ok(Arc<T>),
 }
 
 impl<T> Clone for AccessResult<T> {
     fn clone(&self) -> AccessResult<T> {
         match self {
-            AccessResult::Ok(rc) => AccessResult::Ok(Rc::clone(rc)),
+            AccessResult::Ok(arc) => AccessResult::Ok(Arc::clone(arc)),
             AccessResult::Unavailable => AccessResult::Unavailable,
             AccessResult::Unreachable(st) => AccessResult::Unreachable(*st),
         }
@@ -32,12 +31,22 @@ pub struct Entry<T> {
     pub updated: SystemTime,
 }
 
-type Handle<T> = Arc<Mutex<HashMap<String, Rc<Entry<T>>>>>;
+type Handle<T> = Arc<Mutex<HashMap<String, Arc<Entry<T>>>>>;
 pub struct Cache<T> {
     handle: Handle<T>,
     last_time_shrinked: SystemTime,
 }
 
+/// Clones share the same cache entries.
+impl<T> Clone for Cache<T> {
+    fn clone(&self) -> Self {
+        Self {
+            handle: Arc::clone(&self.handle),
+            last_time_shrinked: self.last_time_shrinked,
+        }
+    }
+}
+
 impl<T> Cache<T> {
     pub fn new(now: SystemTime) -> Self {
         Self {
@@ -46,8 +55,8 @@ impl<T> Cache<T> {
         }
     }
 
-    pub fn get(&self, authority: &str) -> Option<Rc<Entry<T>>> {
-        self.handle.lock().unwrap().get(authority).map(Rc::clone)
+    pub fn get(&self, authority: &str) -> Option<Arc<Entry<T>>> {
+        self.handle.lock().unwrap().get(authority).map(Arc::clone)
     }
 
     pub fn insert(&mut self, authority: &str, ar: AccessResult<T>, now: SystemTime) {
@@ -68,8 +77,8 @@ impl<T> Cache<T> {
                 delete_older /= 2;
             }
         }
-        let entry = Rc::new(Entry { ar, updated: now });
-        map.insert(authority.to_string(), Rc::clone(&entry));
+        let entry = Arc::new(Entry { ar, updated: now });
+        map.insert(authority.to_string(), Arc::clone(&entry));
     }
 
     #[cfg(test)]
@@ -91,7 +100,7 @@ mod tests {
         let ar: AR<()> = AR::Unavailable;
         assert_eq!(ar, ar.clone());
 
-        let ar: AR<bool> = AR::Ok(std::rc::Rc::new(true));
+        let ar: AR<bool> = AR::Ok(std::sync::Arc::new(true));
         assert_eq!(ar, ar.clone());
 
         let ar: AR<i64> = AR::Unreachable(SystemTime::now());
@@ -4,13 +4,18 @@ use crate::fetcher::Fetcher;
 use crate::url_util::with_path_only;
 use anyhow::Result;
 use cache::{AccessResult as AR, Cache as RobotsTxtCache};
-use std::rc::Rc;
+use std::sync::Arc;
 use std::time::SystemTime;
 use texting_robots::Robot;
 use url::Url;
 
 mod cache;
 
+/// RFC 9309 demands to follow at least five consecutive redirects
+const MAX_REDIRECTS: usize = 5;
+
+/// Clones share the cache of parsed robots.txt files.
+#[derive(Clone)]
 pub(super) struct RobotsTxt {
     robotstxt_cache: RobotsTxtCache<Robot>,
     bot_name: String,
@@ -21,7 +26,7 @@ pub(super) enum CheckResult {
     Allowed,
     Disallowed,
     /// Come back later in n seconds
-    Retry(i32),
+    Retry(u64),
 }
 
 impl RobotsTxt {
@@ -107,17 +112,27 @@ impl RobotsTxt {
                     unreachable_first_tried = Some(*first_tried);
                 }
                 _ => (),
-            };
+            }
         }
 
         let robots_url = with_path_only(url, "robots.txt");
-        let fetchresult = fetcher.fetch(&robots_url)?;
+        let mut fetchresult = fetcher.fetch(&robots_url)?;
+        let mut redirects = 0;
+        while let Some(target) = fetchresult.redirect_target().cloned() {
+            if redirects == MAX_REDIRECTS {
+                break;
+            }
+            redirects += 1;
+            fetchresult = fetcher.fetch(&target)?;
+        }
 
         let ar = match fetchresult.status.as_u16() {
-            400..=499 => AR::Unavailable,
+            // RFC 9309: "If there are more than five consecutive redirects,
+            // crawlers MAY assume that the robots.txt file is unavailable."
+            300..=499 => AR::Unavailable,
             200 => {
                 let robot = Robot::new(&self.bot_name, &fetchresult.body);
-                AR::Ok(Rc::new(robot.unwrap()))
+                AR::Ok(Arc::new(robot.unwrap()))
             }
             _ => AR::Unreachable(unreachable_first_tried.unwrap_or(fetchresult.start)),
         };
@@ -9,78 +9,457 @@
 //!
 //! [itir]: https://nlp.stanford.edu/IR-book/html/htmledition/the-url-frontier-1.html
 //!
-//! public API:
-//! - new(db_connection, max_jobs)
-//!   - spawn crawlers, set up queues
-//!   - foreach crawl_job send url
-//! - spawns crawl_job with initial url
-//! - put_urls(crawl_job, fetch_result, discovered_urls) -> Option<new_url>
-//!   - update politeness for fetched domain
-//!   - update heartbeat (locally) for crawl_job
-//!   - try send url, collect empty queues
-//!   - db stuff
-//!     - insert new urls
-//!     - select new urls
-//!   - maybe send url or send crawler shutdown
-//! - monitors CTRL-C signal and returns None on put_urls calls
-//! - send url can contain a waiting time
-//!
-//! internal:
-//! - get_url_for_crawl_job(crawl_job)
-//!   - collect domains from queues for crawl job
-//!   - Politeness.get_domains_min_waiting(Vec<domain>) -> (duration, Vec<domain>)
-//!     - returns 0 waiting time for unknown domain
-//!   - foreach domain: peek queue, search url with max priority
-//!   - pop url from queue
-//!   - send(url, waiting duration)
-//!   - if queue.len for domain is < 2
-//!     - refill queues (for all crawl_jobs?)
-
-use crate::db;
+//! Design:
+//! - Each crawler instance leases one crawl job at a time from the database
+//!   and renews the lease while it works on it. A job without crawlable urls
+//!   is finished, a job with only deferred urls is released.
+//! - The urls of the job are kept in one queue per authority, highest
+//!   priority first. Queues are refilled from the database when one gets
+//!   short, urls already queued are excluded.
+//! - [`UrlFrontier::get_item`] hands out a url of the authority whose
+//!   politeness delay ends first and marks the authority busy until the
+//!   worker [releases](UrlFrontier::release) it, so an authority is crawled
+//!   by one worker at a time.
+//! - Urls fetched successfully within their revisit interval are not
+//!   selected again. Urls that can not be crawled right now are put back with
+//!   a `not_before` time.
+//! - Outlinks are stored with the depth and priority of the job, see
+//!   [`priority`].
+
+use crate::clock;
+use crate::crawl_job::CrawlJob;
+use crate::db::{self, NewUrl};
 use anyhow::Result;
 use diesel::pg::PgConnection;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, SystemTime};
 use url::Url;
 
-use crate::crawler::{Inlink, Outlink, UrlItem};
+use crate::crawler::{Context, Outlink, UrlItem};
+use crate::fetcher::FetchResult;
+use crate::politeness::SharedPoliteness;
+
+/// Deferred urls coming up later than this are left for the next run
+const MAX_DEFERRED_WAIT: Duration = Duration::from_mins(15);
+/// Duration of a crawl job lease, renewed after a third has passed
+const LEASE_DURATION: Duration = Duration::from_mins(10);
+/// Workers waiting for deferred urls ask again after this at the latest, so
+/// the lease is renewed in time
+const MAX_WAIT: Duration = Duration::from_secs(LEASE_DURATION.as_secs() / 4);
+/// Number of urls selected per domain when refilling the queues
+const QUEUE_REFILL_LEN: i32 = 10;
+/// Queues are refilled if one gets shorter than this
+const QUEUE_MIN_LEN: usize = 2;
+
+// Factors of the url priority, see [`priority`]
+const HOP_FACTOR: f32 = 0.9;
+const PATH_SEGMENT_FACTOR: f32 = 0.95;
+const QUERY_PARAM_FACTOR: f32 = 0.8;
+/// Default priority of the sitemap protocol
+const SITEMAP_DEFAULT_PRIORITY: f32 = 0.5;
+/// 0 means "don't crawl"
+const MIN_PRIORITY: f32 = 0.001;
+/// A url is skipped by the job after this number of failed fetches in a row
+const MAX_FETCH_ERRORS: i64 = 3;
+/// Wait before retrying a failed fetch, doubled on every further failure
+const RETRY_BACKOFF: Duration = Duration::from_mins(1);
+
+/// Answer of [`UrlFrontier::get_item`]
+pub enum Next {
+    /// Crawl this url, then release its host
+    Url(UrlItem),
+    /// All urls left belong to hosts busy with other workers
+    Busy,
+    /// Only deferred urls are left, ask again at this time
+    Wait(SystemTime),
+    /// No crawl job is left
+    Done,
+}
 
 pub struct UrlFrontier {
     conn: PgConnection,
-    domain: String,
-    urls: Vec<Url>,
-    /// urls received already from the DB to be excluded from SELECTs
-    url_ids_received: Vec<i32>,
+    /// Name of this crawler instance for crawl job leases
+    owner: String,
+    job: Option<CrawlJob>,
+    lease_renewed: SystemTime,
+    /// jobs with only deferred urls left, not leased again in this run
+    jobs_released: Vec<i32>,
+    /// urls to crawl by authority, highest priority first
+    queues: HashMap<String, VecDeque<UrlItem>>,
+    /// A queue fell below `QUEUE_MIN_LEN`
+    refill: bool,
+    /// hosts of urls handed out and not yet released by their worker
+    busy_hosts: HashSet<String>,
+    politeness: SharedPoliteness,
+    /// feeds recorded by [`Self::put_feeds`] with the host offering them
+    known_feeds: HashSet<(String, String)>,
+    /// urls in the queues or handed out and not yet released, excluded from
+    /// SELECTs. Crawled urls are excluded by their fetch log, urls that could
+    /// not be crawled by their `not_before`.
+    url_ids_queued: HashSet<i32>,
+}
+
+impl Drop for UrlFrontier {
+    fn drop(&mut self) {
+        if let Some(job) = &self.job {
+            debug!("releasing crawl job {}", job.id);
+            if let Err(e) = db::release_crawl_job(&mut self.conn, job.id, false) {
+                error!("{e:?}");
+            }
+        }
+    }
 }
 
 impl UrlFrontier {
-    pub fn new() -> Result<Self> {
+    pub fn new(politeness: SharedPoliteness) -> Result<Self> {
+        let hostname = hostname::get()?;
         Ok(UrlFrontier {
             conn: db::init_conn()?,
-            domain: String::from("de.populus.wiki"),
-            urls: vec![Url::parse("https://de.populus.wiki").unwrap()],
-            url_ids_received: vec![],
+            owner: format!("{}:{}", hostname.to_string_lossy(), std::process::id()),
+            job: None,
+            lease_renewed: SystemTime::UNIX_EPOCH,
+            jobs_released: vec![],
+            queues: HashMap::new(),
+            refill: false,
+            busy_hosts: HashSet::new(),
+            politeness,
+            known_feeds: HashSet::new(),
+            url_ids_queued: HashSet::new(),
         })
     }
 
+    /// Returns false if there is no crawl job to lease
+    fn lease_job(&mut self) -> Result<bool> {
+        let Some(m) = db::lease_crawl_job(
+            &mut self.conn,
+            &self.owner,
+            LEASE_DURATION,
+            &self.jobs_released,
+        )?
+        else {
+            info!("No crawl job available");
+            return Ok(false);
+        };
+        let job = CrawlJob::from(m);
+        let seeds: Vec<NewUrl> = job
+            .seeds
+            .iter()
+            .map(|url| NewUrl {
+                url,
+                depth: 0,
+                priority: 1.0,
+            })
+            .collect();
+        db::insert_urls(&mut self.conn, job.id, &seeds)?;
+        info!(
+            "Leased crawl job {} with max depth {} and scope {:?}",
+            job.id, job.max_depth, job.scope
+        );
+        self.lease_renewed = SystemTime::now();
+        self.job = Some(job);
+        Ok(true)
+    }
+
+    fn renew_lease(&mut self, job_id: i32) -> Result<()> {
+        if clock::elapsed(self.lease_renewed, LEASE_DURATION.as_secs() / 3) {
+            db::renew_crawl_job_lease(&mut self.conn, job_id, &self.owner, LEASE_DURATION)?;
+            self.lease_renewed = SystemTime::now();
+        }
+        Ok(())
+    }
+
+    /// Marks the crawl job finished unless deferred urls are left.
+    fn end_job(&mut self, job_id: i32) -> Result<()> {
+        let deferred = db::select_next_not_before(&mut self.conn, job_id)?.is_some();
+        if deferred {
+            info!("Releasing crawl job {job_id} with deferred urls left");
+            self.jobs_released.push(job_id);
+        } else {
+            info!("Finished crawl job {job_id}");
+        }
+        db::release_crawl_job(&mut self.conn, job_id, !deferred)?;
+        self.job = None;
+        self.queues.clear();
+        self.url_ids_queued.clear();
+        Ok(())
+    }
+
+    /// Refills the queues shorter than `QUEUE_MIN_LEN` and adds queues of
+    /// hosts without one.
     fn fill_urls(&mut self) -> Result<()> {
-        let url_models =
-            db::select_crawl_urls(&mut self.conn, &self.domain, &self.url_ids_received)?;
+        self.refill = false;
+        let job = self.job.as_ref().expect("leased job");
+        let filled_hosts: Vec<&str> = self
+            .queues
+            .iter()
+            .filter(|(_, queue)| queue.len() >= QUEUE_MIN_LEN)
+            .map(|(host, _)| host.as_str())
+            .collect();
+        let queued: Vec<i32> = self.url_ids_queued.iter().copied().collect();
+        let url_models = db::select_crawl_urls(
+            &mut self.conn,
+            job.id,
+            job.max_depth,
+            &queued,
+            &filled_hosts,
+            QUEUE_REFILL_LEN,
+        )?;
         for (u, d) in url_models {
             // todo: how does into() in rust work?
-            self.urls.push(u.to_url(&d.name));
-            self.url_ids_received.push(u.url_id);
+            self.url_ids_queued.insert(u.url_id);
+            let item = UrlItem {
+                url_id: Some(u.url_id),
+                validators: u.validators(),
+                depth: u.crawl_depth.unwrap_or_default(),
+                priority: u.crawl_priority.unwrap_or(1.0),
+                ..UrlItem::new(u.to_url(&d.name))
+            };
+            self.queues
+                .entry(item.url.authority().to_string())
+                .or_default()
+                .push_back(item);
+        }
+        for queue in self.queues.values_mut() {
+            queue
+                .make_contiguous()
+                .sort_by(|a, b| b.priority.total_cmp(&a.priority));
         }
         Ok(())
     }
 
-    pub fn get_item(&mut self) -> Result<Option<UrlItem>> {
-        if self.urls.is_empty() {
-            self.fill_urls()?;
+    /// Pops the url with the highest priority of the host that has been
+    /// waiting the longest for its next fetch. Hosts busy with another
+    /// worker are skipped.
+    fn pop_item(&mut self) -> Option<UrlItem> {
+        let (waiting, hosts) = self.politeness.lock().unwrap().get_domains_min_waiting(
+            self.queues
+                .keys()
+                .map(String::as_str)
+                .filter(|host| !self.busy_hosts.contains(*host)),
+        );
+        let host = (*hosts.first()?).to_string();
+        if !waiting.is_zero() {
+            debug!("No host ready, {host} is in {} ms", waiting.as_millis());
+        }
+
+        let queue = self.queues.get_mut(&host)?;
+        let item = queue.pop_front();
+        if queue.len() < QUEUE_MIN_LEN {
+            self.refill = true;
+        }
+        if queue.is_empty() {
+            self.queues.remove(&host);
+        }
+        self.busy_hosts.insert(host);
+        item
+    }
+
+    /// The host of a url handed out by [`Self::get_item`] stays busy until
+    /// the worker releases it.
+    pub fn get_item(&mut self) -> Result<Next> {
+        loop {
+            if self.job.is_none() && !self.lease_job()? {
+                return Ok(Next::Done);
+            }
+            let job_id = self.job.as_ref().expect("leased above").id;
+            self.renew_lease(job_id)?;
+
+            if self.refill || self.queues.is_empty() {
+                self.fill_urls()?;
+            }
+            if let Some(item) = self.pop_item() {
+                return Ok(Next::Url(item));
+            }
+            if !self.busy_hosts.is_empty() {
+                // Workers might still add outlinks or put urls back
+                return Ok(Next::Busy);
+            }
+            // Only deferred urls might be left. Wait for them if it's worth it.
+            let next = db::select_next_not_before(&mut self.conn, job_id)?;
+            if let Some(not_before) = next.map(SystemTime::from) {
+                if clock::until(not_before) < MAX_DEFERRED_WAIT {
+                    debug!("Waiting for deferred urls of crawl job {job_id}");
+                    return Ok(Next::Wait(not_before.min(SystemTime::now() + MAX_WAIT)));
+                }
+            }
+            self.end_job(job_id)?;
+        }
+    }
+
+    /// Allows other workers to crawl urls of this host again. The url may
+    /// be selected again unless it has been fetched or deferred.
+    pub fn release(&mut self, item_url: &Url, url_id: Option<i32>) {
+        self.busy_hosts.remove(item_url.authority());
+        if let Some(url_id) = url_id {
+            self.url_ids_queued.remove(&url_id);
         }
-        Ok(self.urls.pop().map(|url| UrlItem { i: vec![], url }))
     }
 
-    pub fn put_outlinks(&mut self, _url: &Url, outlinks: &[Outlink]) -> Result<usize> {
-        let urls = outlinks.iter().map(|o| o.url.clone()).collect();
-        db::insert_urls(&mut self.conn, &urls)
+    /// Whether the url belongs to the current crawl job
+    pub fn in_scope(&self, url: &Url) -> bool {
+        self.job.as_ref().is_some_and(|job| job.scope.contains(url))
+    }
+
+    pub fn job_id(&self) -> i32 {
+        self.job.as_ref().expect("get_item leases a job").id
+    }
+
+    /// Leaves out a url that can not be crawled, e.g. because robots.txt
+    /// forbids it, for the rest of the crawl job
+    pub fn skip(&mut self, url_id: i32) -> Result<()> {
+        let job_id = self.job_id();
+        db::skip_crawl_url(&mut self.conn, job_id, url_id)
+    }
+
+    /// Puts back an item that could not be crawled right now. It is not handed
+    /// out again before the given time.
+    pub fn put_back(&mut self, item: &UrlItem, not_before: SystemTime) -> Result<()> {
+        let job_id = self.job_id();
+        let new_url = NewUrl {
+            url: &item.url,
+            depth: item.depth,
+            priority: item.priority,
+        };
+        db::insert_urls(&mut self.conn, job_id, &[new_url])?;
+        db::defer_url(&mut self.conn, &item.url, not_before.into())?;
+        Ok(())
+    }
+
+    /// Puts back an item whose fetch failed, e.g. on a timeout. It is retried
+    /// with exponential back-off and skipped after [`MAX_FETCH_ERRORS`]
+    /// failures in a row.
+    pub fn put_failed(&mut self, item: &UrlItem) -> Result<()> {
+        let id = match item.url_id {
+            Some(id) => id,
+            None => self.url_id(&item.url)?,
+        };
+        let errors = db::count_fetch_errors(&mut self.conn, id)?;
+        if errors >= MAX_FETCH_ERRORS {
+            info!("Skipping {} after {errors} failed fetches", item.url);
+            return self.skip(id);
+        }
+        let exponent = u32::try_from(errors.saturating_sub(1)).unwrap_or(0);
+        let backoff = RETRY_BACKOFF * 2u32.pow(exponent);
+        self.put_back(item, SystemTime::now() + backoff)
+    }
+
+    /// Stores the cache validators of a successful fetch for the next fetch
+    pub fn put_validators(&mut self, item: &UrlItem, fr: &FetchResult) -> Result<()> {
+        let Some(url_id) = item.url_id else {
+            return Ok(());
+        };
+        db::update_validators(&mut self.conn, url_id, &fr.validators)
+    }
+
+    /// Row of the url, inserted if the url is unknown
+    pub fn url_id(&mut self, url: &Url) -> Result<i32> {
+        db::select_url_id(&mut self.conn, url)
+    }
+
+    /// Crawls the target of a permanently redirected URL instead of the URL
+    pub fn put_redirect(&mut self, from: &Url, to: &Url) -> Result<()> {
+        db::replace_url(&mut self.conn, from, to)
+    }
+
+    /// Records feeds offered by the domain of `site`. Most pages of a site
+    /// announce the same feeds, only new ones are written to the DB.
+    pub fn put_feeds(&mut self, site: &Url, feeds: &[&Url]) -> Result<()> {
+        let host = site.host_str().unwrap_or_default();
+        let new: Vec<&Url> = feeds
+            .iter()
+            .filter(|f| {
+                !self
+                    .known_feeds
+                    .contains(&(host.to_string(), f.to_string()))
+            })
+            .copied()
+            .collect();
+        db::insert_domain_feeds(&mut self.conn, site, &new)?;
+        self.known_feeds
+            .extend(new.iter().map(|f| (host.to_string(), f.to_string())));
+        Ok(())
+    }
+
+    pub fn put_outlinks(&mut self, item: &UrlItem, outlinks: &[Outlink]) -> Result<usize> {
+        let urls: Vec<NewUrl> = outlinks
+            .iter()
+            .map(|o| NewUrl {
+                url: &o.url,
+                depth: item.depth.saturating_add(1),
+                priority: priority(o, item.priority),
+            })
+            .collect();
+        let job_id = self.job_id();
+        db::insert_urls(&mut self.conn, job_id, &urls)
+    }
+}
+
+/// Priority of an outlink, see "url priority" in README-dev.org:
+///
+/// - sitemaps have priority 1
+/// - links from sitemaps have the priority given in the sitemap
+/// - other links inherit a fraction of the priority of the linking url, less
+///   for every path segment and even less for every query parameter
+pub fn priority(outlink: &Outlink, parent_priority: f32) -> f32 {
+    let p = match outlink.i.context {
+        Context::Sitemap => 1.0,
+        Context::SitemapLink => outlink.i.priority.unwrap_or(SITEMAP_DEFAULT_PRIORITY),
+        _ => {
+            let url = &outlink.url;
+            let segments = url
+                .path_segments()
+                .map_or(0, |s| s.filter(|s| !s.is_empty()).count());
+            let params = url.query_pairs().count();
+            parent_priority
+                * HOP_FACTOR
+                * PATH_SEGMENT_FACTOR.powi(i32::try_from(segments).unwrap_or(i32::MAX))
+                * QUERY_PARAM_FACTOR.powi(i32::try_from(params).unwrap_or(i32::MAX))
+        }
+    };
+    if p.is_nan() {
+        // broken sitemap
+        return MIN_PRIORITY;
+    }
+    p.clamp(MIN_PRIORITY, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{priority, MIN_PRIORITY};
+    use crate::crawler::{Context, Inlink, Outlink};
+    use url::Url;
+
+    fn outlink(url: &str, context: Context, p: Option<f32>) -> Outlink {
+        Outlink {
+            url: Url::parse(url).unwrap(),
+            i: Inlink {
+                context,
+                priority: p,
+                ..Inlink::default()
+            },
+        }
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn priorities() {
+        let sitemap = outlink("https://a.org/sitemap.xml", Context::Sitemap, None);
+        assert_eq!(priority(&sitemap, 0.1), 1.0);
+
+        let clamped = |p| {
+            priority(
+                &outlink("https://a.org/x", Context::SitemapLink, Some(p)),
+                1.0,
+            )
+        };
+        assert_eq!(clamped(0.3), 0.3);
+        assert_eq!(clamped(7.0), 1.0);
+        assert_eq!(clamped(0.0), MIN_PRIORITY);
+        assert_eq!(clamped(f32::NAN), MIN_PRIORITY);
+
+        let p = |url| priority(&outlink(url, Context::Other, None), 1.0);
+        assert!(p("https://a.org/") < 1.0);
+        assert!(p("https://a.org/a/b") < p("https://a.org/a"));
+        assert!(p("https://a.org/a?b=c") < p("https://a.org/a/b"));
     }
 }
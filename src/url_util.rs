@@ -1,4 +1,4 @@
-use url::Url;
+use url::{Position, Url};
 
 /// Used by crawler to give special treatment to main site of a domain
 pub fn is_domain_root(url: &Url) -> bool {
@@ -20,6 +20,13 @@ pub fn with_path_only(url: &Url, path: &str) -> Url {
         .expect("Started from a valid URL")
 }
 
+/// Used for crawl job scopes: the host and, if it is not the default port of
+/// the scheme, the port of the url
+pub fn host_port(url: &Url) -> Option<&str> {
+    url.has_host()
+        .then(|| &url[Position::BeforeHost..Position::AfterPort])
+}
+
 /// Used by HTML link extractor to only collect links to HTTP resources
 pub fn is_http_s(url: &Url) -> bool {
     url.scheme() == "http" || url.scheme() == "https"
@@ -30,7 +37,8 @@ pub fn is_http_s(url: &Url) -> bool {
 pub fn build(domain: &str, path: &str, query: Option<&str>) -> Url {
     let mut s = format!("https://{domain}{path}");
     if let Some(q) = query {
-        s.push_str(&format!("?{q}"));
+        s.push('?');
+        s.push_str(q);
     }
     Url::parse(&s).unwrap()
 }
@@ -0,0 +1,357 @@
+//! CDXJ index of the archive, one sorted `.cdxj` file next to each WARC
+//! file, or a `.cdxj.unsorted` file while the WARC file is written.
+//! Each line holds the SURT of the url, the 14 digit timestamp and a JSON
+//! object locating the record:
+//!
+//! `org,example)/page 20261018120000 {"digest":"sha1:...","filename":"...","length":"...","mime":"text/html","offset":"...","status":"200","url":"https://example.org/page"}`
+//!
+//! See <https://specs.webrecorder.net/cdxj/0.1.0/> and
+//! <https://pywb.readthedocs.io/en/latest/manual/indexing.html>
+
+use super::reader::{self, HttpResponse};
+use super::{Record, WarcLocation};
+use crate::env_config::ARCHIVE_DIR;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, ErrorKind, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use url::Url;
+
+/// Index of a WARC file still being written, appended in the order of the
+/// records and sorted into the `.cdxj` file when the WARC file is closed
+const UNSORTED_SUFFIX: &str = ".cdxj.unsorted";
+
+/// Media type of revisit records in the index
+pub const REVISIT_MIME: &str = "warc/revisit";
+
+/// A capture of a url as listed in the index
+#[derive(Clone, Debug)]
+pub struct Capture {
+    pub timestamp: String,
+    pub url: String,
+    pub mime: String,
+    pub status: String,
+    pub location: WarcLocation,
+}
+
+impl Capture {
+    /// Parses an index line
+    pub fn parse(line: &str) -> Option<Self> {
+        let (_key, rest) = line.split_once(' ')?;
+        let (timestamp, json) = rest.split_once(' ')?;
+        let fields: Value = serde_json::from_str(json).ok()?;
+        let field = |name| fields.get(name).and_then(Value::as_str).map(String::from);
+        Some(Self {
+            timestamp: timestamp.to_string(),
+            url: field("url")?,
+            mime: field("mime").unwrap_or_default(),
+            status: field("status").unwrap_or_default(),
+            location: WarcLocation {
+                filename: field("filename")?,
+                offset: field("offset")?.parse().ok()?,
+                length: field("length")?.parse().ok()?,
+            },
+        })
+    }
+
+    pub fn is_revisit(&self) -> bool {
+        self.mime == REVISIT_MIME
+    }
+}
+
+/// All captures of the url in the index files in `ARCHIVE_DIR`, oldest first
+pub fn captures(url: &Url) -> Result<Vec<Capture>> {
+    Ok(captures_of(&[url])?.pop().unwrap_or_default())
+}
+
+/// The captures of each url, oldest first. Each index file is opened once,
+/// sorted files are searched and only the unsorted files of WARC files still
+/// being written are read completely.
+pub fn captures_of(urls: &[&Url]) -> Result<Vec<Vec<Capture>>> {
+    let prefixes: Vec<String> = urls.iter().map(|u| format!("{} ", surt(u))).collect();
+    let mut captures = vec![Vec::new(); urls.len()];
+    for entry in fs::read_dir(ARCHIVE_DIR.get())? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let sorted = name.ends_with(".cdxj");
+        if !sorted && !name.ends_with(UNSORTED_SUFFIX) {
+            continue;
+        }
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            // An unsorted file has just been sorted
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let mut reader = BufReader::new(file);
+        if sorted {
+            for (prefix, captures) in prefixes.iter().zip(&mut captures) {
+                captures.extend(search(&mut reader, prefix)?);
+            }
+        } else {
+            for line in reader.lines() {
+                let line = line?;
+                for (prefix, captures) in prefixes.iter().zip(&mut captures) {
+                    if line.starts_with(prefix.as_str()) {
+                        captures.extend(Capture::parse(&line));
+                    }
+                }
+            }
+        }
+    }
+    for captures in &mut captures {
+        captures.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    }
+    Ok(captures)
+}
+
+/// Captures of the lines starting with `prefix` in a sorted index
+fn search<R: BufRead + Seek>(index: &mut R, prefix: &str) -> Result<Vec<Capture>> {
+    let start = lower_bound(index, prefix.as_bytes())?;
+    index.seek(SeekFrom::Start(start))?;
+    let mut captures = Vec::new();
+    // The lines of the url are next to each other in the sorted file
+    for line in index.lines() {
+        let line = line?;
+        if line.starts_with(prefix) {
+            captures.extend(Capture::parse(&line));
+        } else if line.as_str() > prefix {
+            break;
+        }
+    }
+    Ok(captures)
+}
+
+/// Binary search for the start of a line not after the first line that is
+/// not less than `key`. All lines before the returned offset are less than
+/// `key`.
+fn lower_bound<R: BufRead + Seek>(index: &mut R, key: &[u8]) -> Result<u64> {
+    let (mut lo, mut hi) = (0, index.seek(SeekFrom::End(0))?);
+    let mut line = Vec::new();
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        index.seek(SeekFrom::Start(mid))?;
+        let mut start = mid;
+        if mid > lo {
+            // Skip the rest of the line around mid
+            start += index.read_until(b'\n', &mut line)? as u64;
+            line.clear();
+        }
+        if start >= hi {
+            hi = mid;
+            continue;
+        }
+        let len = index.read_until(b'\n', &mut line)? as u64;
+        if line.as_slice() < key {
+            lo = start + len;
+        } else {
+            hi = start;
+        }
+        line.clear();
+    }
+    Ok(lo)
+}
+
+/// Sort-friendly URL key: reversed host without `www.`, lowercased path and
+/// sorted query parameters, e.g. `org,example)/path?a=1&b=2`
+pub fn surt(url: &Url) -> String {
+    let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    let mut key = host.split('.').rev().collect::<Vec<_>>().join(",");
+    if let Some(port) = url.port() {
+        key.push(':');
+        key.push_str(&port.to_string());
+    }
+    key.push(')');
+    key.push_str(&url.path().to_ascii_lowercase());
+    if let Some(query) = url.query() {
+        let mut params: Vec<&str> = query.split('&').collect();
+        params.sort_unstable();
+        key.push('?');
+        key.push_str(&params.join("&").to_ascii_lowercase());
+    }
+    key
+}
+
+/// 14 digit timestamp as used in CDX files and Wayback URLs
+pub fn timestamp(date: SystemTime) -> String {
+    let dt: DateTime<Utc> = date.into();
+    dt.format("%Y%m%d%H%M%S").to_string()
+}
+
+/// Index line of response and revisit records
+pub fn line(record: &Record, location: &WarcLocation) -> Option<String> {
+    if !matches!(record.warc_type.as_str(), "response" | "revisit") {
+        return None;
+    }
+    let url = record.get("WARC-Target-URI")?;
+    let key = surt(&Url::parse(url).ok()?);
+    let http = HttpResponse::parse(&record.block).ok();
+    let mime = if record.warc_type == "revisit" {
+        REVISIT_MIME
+    } else {
+        http.as_ref().and_then(HttpResponse::mime).unwrap_or("unk")
+    };
+    let status = http.map_or_else(|| "-".to_string(), |h| h.status.to_string());
+    let fields = json!({
+        "url": url,
+        "mime": mime,
+        "status": status,
+        "digest": record.get("WARC-Payload-Digest").unwrap_or("-"),
+        "length": location.length.to_string(),
+        "offset": location.offset.to_string(),
+        "filename": location.filename,
+    });
+    Some(format!("{key} {} {fields}", timestamp(record.date)))
+}
+
+/// `archive_000.warc.gz` is indexed in `archive_000.cdxj`
+pub fn index_path(warc_path: &Path) -> PathBuf {
+    warc_path.with_file_name(format!("{}.cdxj", stem(warc_path)))
+}
+
+/// `archive_000.warc.gz` is indexed in `archive_000.cdxj.unsorted` while
+/// it is written
+pub fn unsorted_index_path(warc_path: &Path) -> PathBuf {
+    warc_path.with_file_name(format!("{}{UNSORTED_SUFFIX}", stem(warc_path)))
+}
+
+fn stem(warc_path: &Path) -> String {
+    let name = warc_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    name.strip_suffix(".warc.gz")
+        .or_else(|| name.strip_suffix(".warc"))
+        .unwrap_or(&name)
+        .to_string()
+}
+
+/// Sorts the unsorted index of a closed WARC file into its `.cdxj` file
+pub fn sort(warc_path: &Path) -> Result<()> {
+    let unsorted = unsorted_index_path(warc_path);
+    let content = fs::read_to_string(&unsorted)?;
+    let mut lines: Vec<&str> = content.lines().collect();
+    lines.sort_unstable();
+    write_sorted(&index_path(warc_path), &lines)?;
+    fs::remove_file(unsorted)?;
+    Ok(())
+}
+
+fn write_sorted(path: &Path, lines: &[&str]) -> Result<()> {
+    let tmp = path.with_extension("cdxj.tmp");
+    let mut content = lines.join("\n");
+    if !content.is_empty() {
+        content.push('\n');
+    }
+    fs::write(&tmp, content)?;
+    fs::rename(tmp, path)?;
+    Ok(())
+}
+
+/// Command `index [WARC_FILE...]`: Rebuilds the index of the given WARC
+/// files, by default of all files in `ARCHIVE_DIR`.
+pub fn rebuild(args: &[String]) -> Result<()> {
+    for path in super::archive_files(args)? {
+        let filename = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let mut lines = Vec::new();
+        for item in reader::open(&path)? {
+            let item = item.with_context(|| path.display().to_string())?;
+            let Some(length) = item.length else {
+                warn!(
+                    "{}: Skipping record in gzip member at {} with more records",
+                    path.display(),
+                    item.offset
+                );
+                continue;
+            };
+            let location = WarcLocation {
+                filename: filename.clone(),
+                offset: item.offset,
+                length,
+            };
+            lines.extend(line(&item.record, &location));
+        }
+        let mut lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        lines.sort_unstable();
+        let index = index_path(&path);
+        write_sorted(&index, &lines)?;
+        // Left over by a crawler that did not close the WARC file
+        if fs::remove_file(unsorted_index_path(&path)).is_ok() {
+            debug!("Removed the unsorted index of {}", path.display());
+        }
+        info!("Indexed {} records in {}", lines.len(), index.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{search, surt, Capture};
+    use std::io::Cursor;
+    use url::Url;
+
+    #[test]
+    fn surt_keys() {
+        let s = |u: &str| surt(&Url::parse(u).unwrap());
+        assert_eq!(s("https://www.Example.org/"), "org,example)/");
+        assert_eq!(
+            s("https://a.example.org/Path?b=2&a=1"),
+            "org,example,a)/path?a=1&b=2"
+        );
+        assert_eq!(s("http://example.org:8080/x"), "org,example:8080)/x");
+    }
+
+    #[test]
+    fn parse_capture() {
+        let c = Capture::parse(
+            r#"org,example)/ 20261018120000 {"digest":"sha1:X","filename":"a.warc.gz","length":"10","mime":"text/html","offset":"20","status":"200","url":"https://example.org/"}"#,
+        )
+        .unwrap();
+        assert_eq!(c.timestamp, "20261018120000");
+        assert_eq!(c.url, "https://example.org/");
+        assert_eq!((c.location.offset, c.location.length), (20, 10));
+        assert!(!c.is_revisit());
+        assert!(Capture::parse("org,example)/ 20261018120000 {}").is_none());
+    }
+
+    #[test]
+    fn search_sorted_index() {
+        let line = |key: &str, ts: &str| {
+            format!(
+                r#"{key} {ts} {{"filename":"a.warc.gz","length":"1","offset":"0","url":"https://{key}"}}"#
+            )
+        };
+        let mut lines = Vec::new();
+        for i in 0..50 {
+            lines.push(line(&format!("org,example)/{i:02}"), "20261018120000"));
+        }
+        lines.push(line("org,example)/17", "20261018130000"));
+        lines.sort();
+        let mut index = Cursor::new(lines.join("\n"));
+        let found = |index: &mut Cursor<String>, key: &str| -> Vec<String> {
+            search(index, &format!("{key} "))
+                .unwrap()
+                .into_iter()
+                .map(|c| c.timestamp)
+                .collect()
+        };
+        assert_eq!(
+            found(&mut index, "org,example)/17"),
+            ["20261018120000", "20261018130000"]
+        );
+        assert_eq!(found(&mut index, "org,example)/00"), ["20261018120000"]);
+        assert_eq!(found(&mut index, "org,example)/49"), ["20261018120000"]);
+        assert!(found(&mut index, "org,example)/1").is_empty());
+        assert!(found(&mut index, "org,example)/50").is_empty());
+        assert!(found(&mut index, "com,example)/").is_empty());
+    }
+}
@@ -0,0 +1,351 @@
+//! Writes the fetched responses to WARC files and indexes them.
+//!
+//! WARC 1.1 spec:
+//! <https://github.com/iipc/warc-specifications/blob/master/specifications/warc-format/warc-1.1-annotated/index.md>
+
+use crate::env_config::{ARCHIVE_DIR, WARC_MAX_SIZE};
+use anyhow::Result;
+use chrono::prelude::*;
+use data_encoding::BASE32;
+use flate2::{write::GzEncoder, Compression};
+use sha1::{Digest, Sha1};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+use uuid::Uuid;
+
+pub mod cdxj;
+pub mod reader;
+pub mod wacz;
+
+/// Default for `WARC_MAX_SIZE`, the size of a compressed WARC file after
+/// which a new one is started
+const DEFAULT_MAX_SIZE: u64 = 1_000_000_000;
+/// Prefix of the WARC file names
+const FILE_PREFIX: &str = "lara";
+
+/// Name, version and home page of the software writing the archive
+const SOFTWARE: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION"),
+    " https://github.com/thkoch2001/lara"
+);
+
+const CONFORMS_TO: &str =
+    "http://iipc.github.io/warc-specifications/specifications/warc-format/warc-1.1/";
+
+/// Position of a record in the WARC archive
+#[derive(Clone, Debug)]
+pub struct WarcLocation {
+    pub filename: String,
+    /// Offset of the gzip member holding the record
+    pub offset: u64,
+    /// Length of the gzip member
+    pub length: u64,
+}
+
+/// A WARC record. `WARC-Type`, `WARC-Record-ID`, `WARC-Date`,
+/// `Content-Length` and `WARC-Block-Digest` are written first, followed by
+/// the other fields. Records read from a file keep all other header fields
+/// in `fields`.
+pub struct Record {
+    pub warc_type: String,
+    pub id: String,
+    pub date: SystemTime,
+    pub fields: Vec<(String, String)>,
+    pub block: Vec<u8>,
+}
+
+impl Record {
+    pub fn new(warc_type: &str, date: SystemTime) -> Self {
+        Self {
+            warc_type: warc_type.to_string(),
+            id: Uuid::new_v4().urn().to_string(),
+            date,
+            fields: vec![],
+            block: vec![],
+        }
+    }
+
+    pub fn field(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.fields.push((name.to_string(), value.into()));
+        self
+    }
+
+    /// Value of the first field with the given name, ignoring case
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Compresses the record into a gzip member of its own, so that it can be
+    /// read without decompressing the records before it.
+    fn to_gzip_member(&self) -> io::Result<Vec<u8>> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        self.write_to(&mut encoder)?;
+        encoder.finish()
+    }
+
+    fn write_to(&self, w: &mut impl Write) -> io::Result<usize> {
+        let mut header = format!(
+            "WARC/1.1\r\nWARC-Type: {}\r\nWARC-Record-ID: <{}>\r\nWARC-Date: {}\r\nContent-Length: {}\r\nWARC-Block-Digest: {}\r\n",
+            self.warc_type,
+            self.id,
+            warc_date(self.date),
+            self.block.len(),
+            digest(&self.block)
+        );
+        push_fields(&mut header, &self.fields);
+        header.push_str("\r\n");
+
+        w.write_all(header.as_bytes())?;
+        w.write_all(&self.block)?;
+        w.write_all(b"\r\n\r\n")?;
+        Ok(header.len() + self.block.len() + 4)
+    }
+}
+
+/// Appends `name: value` lines as used in WARC headers and `application/warc-fields`
+fn push_fields(s: &mut String, fields: &[(impl AsRef<str>, String)]) {
+    for (name, value) in fields {
+        s.push_str(name.as_ref());
+        s.push_str(": ");
+        s.push_str(value);
+        s.push_str("\r\n");
+    }
+}
+
+/// SHA-1 in base32 as commonly used for `WARC-Block-Digest` and
+/// `WARC-Payload-Digest`
+pub fn digest(data: &[u8]) -> String {
+    format!("sha1:{}", BASE32.encode(&Sha1::digest(data)))
+}
+
+/// WARC-Date is a UTC timestamp formatted according to W3CDTF
+pub fn warc_date(date: SystemTime) -> String {
+    let dt: DateTime<Utc> = date.into();
+    dt.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn hostname() -> String {
+    hostname::get()
+        .map(|h| h.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// The given files or all WARC files in `ARCHIVE_DIR`
+pub fn archive_files(args: &[String]) -> Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = args.iter().map(PathBuf::from).collect();
+    if paths.is_empty() {
+        for entry in fs::read_dir(ARCHIVE_DIR.get())? {
+            let path = entry?.path();
+            let name = path.to_string_lossy();
+            if name.ends_with(".warc.gz") || name.ends_with(".warc") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+    }
+    Ok(paths)
+}
+
+/// Serial number for the next WARC file, following the highest one found in
+/// `ARCHIVE_DIR`
+pub fn next_file_serial() -> Result<u32> {
+    let mut next = 0;
+    for entry in fs::read_dir(ARCHIVE_DIR.get())? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        let mut parts = name.splitn(5, '-');
+        if parts.next() != Some(FILE_PREFIX) {
+            continue;
+        }
+        if let Some(Ok(serial)) = parts.nth(2).map(str::parse::<u32>) {
+            next = next.max(serial + 1);
+        }
+    }
+    Ok(next)
+}
+
+/// Writes records to gzipped WARC files in `ARCHIVE_DIR`, one gzip member
+/// per record. Each file starts with a warcinfo record. The records are
+/// indexed in a `.cdxj.unsorted` file that is sorted into the `.cdxj` file
+/// when the WARC file is closed.
+pub struct WarcWriter {
+    dir: PathBuf,
+    file: Option<File>,
+    index: Option<File>,
+    /// Serial number of the next file, shared by the writers of all workers
+    file_cnt: Arc<AtomicU32>,
+    filename: String,
+    /// Size of the current file
+    bytes_written: u64,
+    max_size: u64,
+    user_agent: String,
+    from: String,
+    crawl_job_id: Option<i32>,
+}
+
+impl Drop for WarcWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.close() {
+            error!("{e:?}");
+        }
+    }
+}
+
+impl WarcWriter {
+    pub fn new(file_cnt: Arc<AtomicU32>, user_agent: &str, from: &str) -> Self {
+        let dir = ARCHIVE_DIR.parse::<PathBuf>();
+        let m = dir
+            .metadata()
+            .unwrap_or_else(|_| panic!("Could not get metadata of ARCHIVE_DIR: {}", dir.display()));
+        assert!(m.is_dir(), "Not a dir: {}", dir.display());
+        Self {
+            dir,
+            file: None,
+            index: None,
+            file_cnt,
+            filename: String::new(),
+            bytes_written: 0,
+            max_size: WARC_MAX_SIZE.parse_or(DEFAULT_MAX_SIZE),
+            user_agent: user_agent.to_string(),
+            from: from.to_string(),
+            crawl_job_id: None,
+        }
+    }
+
+    /// Records of different crawl jobs go to different files.
+    pub fn set_crawl_job(&mut self, crawl_job_id: i32) -> Result<()> {
+        if self.crawl_job_id != Some(crawl_job_id) {
+            self.close()?;
+            self.crawl_job_id = Some(crawl_job_id);
+        }
+        Ok(())
+    }
+
+    pub fn close(&mut self) -> Result<()> {
+        if let Some(file) = self.file.take() {
+            debug!("closing archive file {}", self.filename);
+            file.sync_all()?;
+        }
+        if self.index.take().is_some() {
+            cdxj::sort(&self.dir.join(&self.filename))?;
+        }
+        self.bytes_written = 0;
+        Ok(())
+    }
+
+    /// Writes records belonging together, e.g. a response and its request,
+    /// to the same file. A file that reached the maximum size is closed before
+    /// the records are written to a new one.
+    pub fn write(&mut self, records: &[&Record]) -> Result<Vec<WarcLocation>> {
+        if self.bytes_written >= self.max_size {
+            self.close()?;
+        }
+        if self.file.is_none() {
+            self.open()?;
+        }
+        let mut locations = Vec::with_capacity(records.len());
+        for record in records {
+            let location = self.append(record)?;
+            if let Some(line) = cdxj::line(record, &location) {
+                writeln!(self.index.as_mut().expect("opened"), "{line}")?;
+            }
+            locations.push(location);
+        }
+        Ok(locations)
+    }
+
+    fn append(&mut self, record: &Record) -> Result<WarcLocation> {
+        let member = record.to_gzip_member()?;
+        self.file.as_mut().expect("opened").write_all(&member)?;
+        let location = WarcLocation {
+            filename: self.filename.clone(),
+            offset: self.bytes_written,
+            length: member.len() as u64,
+        };
+        self.bytes_written += location.length;
+        Ok(location)
+    }
+
+    /// Starts a new file named after the IIPC convention
+    /// `prefix-job-timestamp-serial-hostname.warc.gz`, e.g.
+    /// `lara-7-20261018120000-00042-crawler1.warc.gz`. Existing files are
+    /// never overwritten.
+    fn open(&mut self) -> Result<()> {
+        let serial = self.file_cnt.fetch_add(1, Ordering::Relaxed);
+        let job = self
+            .crawl_job_id
+            .map_or_else(|| "none".to_string(), |id| id.to_string());
+        self.filename = format!(
+            "{FILE_PREFIX}-{job}-{}-{serial:05}-{}.warc.gz",
+            cdxj::timestamp(SystemTime::now()),
+            hostname()
+        );
+        let path = self.dir.join(&self.filename);
+        debug!("Starting new warc file: {}", path.display());
+        self.file = Some(File::create_new(&path)?);
+        self.index = Some(File::create(cdxj::unsorted_index_path(&path))?);
+        self.bytes_written = 0;
+        self.append(&self.warcinfo())?;
+        Ok(())
+    }
+
+    /// Describes the crawl that wrote the file
+    /// <https://iipc.github.io/warc-specifications/specifications/warc-format/warc-1.1/#warcinfo>
+    fn warcinfo(&self) -> Record {
+        let mut fields = vec![
+            ("software", SOFTWARE.to_string()),
+            ("format", "WARC File Format 1.1".to_string()),
+            ("conformsTo", CONFORMS_TO.to_string()),
+            ("http-header-user-agent", self.user_agent.clone()),
+            ("http-header-from", self.from.clone()),
+            ("hostname", hostname()),
+            ("robots", "classic".to_string()),
+        ];
+        if let Some(id) = self.crawl_job_id {
+            fields.push(("isPartOf", format!("crawl-job-{id}")));
+        }
+        let mut block = String::new();
+        push_fields(&mut block, &fields);
+
+        let mut record = Record::new("warcinfo", SystemTime::now())
+            .field("WARC-Filename", self.filename.clone())
+            .field("Content-Type", "application/warc-fields");
+        record.block = block.into_bytes();
+        record
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Record;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn write_record() {
+        let date = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut record = Record::new("resource", date).field("WARC-Target-URI", "https://a.b/");
+        record.block = b"hello".to_vec();
+        let mut out = Vec::new();
+        let len = record.write_to(&mut out).unwrap();
+        assert_eq!(len, out.len());
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(
+            out,
+            format!(
+                "WARC/1.1\r\nWARC-Type: resource\r\nWARC-Record-ID: <{}>\r\n\
+                 WARC-Date: 2023-11-14T22:13:20Z\r\nContent-Length: 5\r\n\
+                 WARC-Block-Digest: sha1:VL2MMHO4YXUKFWV63YHTWSBM3GXKSQ2N\r\n\
+                 WARC-Target-URI: https://a.b/\r\n\r\nhello\r\n\r\n",
+                record.id
+            )
+        );
+    }
+}
@@ -0,0 +1,395 @@
+//! Reads the records of `.warc` files and of `.warc.gz` files with one or
+//! more gzip members, and validates them.
+
+use super::{digest, Record, WarcLocation};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::DateTime;
+use flate2::bufread::GzDecoder;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::SystemTime;
+
+/// A record and where it has been read from
+pub struct ReadRecord {
+    pub record: Record,
+    /// Offset of the record, in gzipped files of its gzip member
+    pub offset: u64,
+    /// Length of the record or gzip member. None if the gzip member holds
+    /// further records.
+    pub length: Option<u64>,
+}
+
+enum Source<R> {
+    Plain(R),
+    Gz(R),
+    GzMember {
+        decoder: Box<BufReader<GzDecoder<R>>>,
+        offset: u64,
+    },
+    Done,
+}
+
+/// Iterates over the records of a WARC file. Iteration ends after the first
+/// malformed record.
+pub struct Records<R> {
+    source: Source<R>,
+}
+
+/// Opens a `.warc` or `.warc.gz` file
+pub fn open(path: &Path) -> Result<Records<BufReader<File>>> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(if path.extension().is_some_and(|e| e == "gz") {
+        Records::gzip(reader)
+    } else {
+        Records::plain(reader)
+    })
+}
+
+/// Reads the record at the location in a file of the directory
+pub fn read_at(dir: &Path, location: &WarcLocation) -> Result<Record> {
+    let path = dir.join(&location.filename);
+    let mut file = File::open(&path)?;
+    file.seek(SeekFrom::Start(location.offset))?;
+    let mut data = Vec::new();
+    file.take(location.length).read_to_end(&mut data)?;
+    let mut records = if path.extension().is_some_and(|e| e == "gz") {
+        Records::gzip(Cursor::new(data))
+    } else {
+        Records::plain(Cursor::new(data))
+    };
+    let item = records
+        .next()
+        .ok_or_else(|| anyhow!("No record at offset {}", location.offset))?
+        .with_context(|| path.display().to_string())?;
+    Ok(item.record)
+}
+
+impl<R: BufRead + Seek> Records<R> {
+    pub fn plain(reader: R) -> Self {
+        Self {
+            source: Source::Plain(reader),
+        }
+    }
+
+    pub fn gzip(reader: R) -> Self {
+        Self {
+            source: Source::Gz(reader),
+        }
+    }
+
+    fn next_record(&mut self) -> Result<Option<ReadRecord>> {
+        loop {
+            // Errors leave the source Done
+            match std::mem::replace(&mut self.source, Source::Done) {
+                Source::Done => return Ok(None),
+                Source::Plain(mut r) => {
+                    let offset = r.stream_position()?;
+                    let Some(record) = read_record(&mut r)
+                        .with_context(|| format!("Malformed record at offset {offset}"))?
+                    else {
+                        return Ok(None);
+                    };
+                    let length = r.stream_position()? - offset;
+                    self.source = Source::Plain(r);
+                    return Ok(Some(ReadRecord {
+                        record,
+                        offset,
+                        length: Some(length),
+                    }));
+                }
+                Source::Gz(mut r) => {
+                    if r.fill_buf()?.is_empty() {
+                        return Ok(None);
+                    }
+                    let offset = r.stream_position()?;
+                    self.source = Source::GzMember {
+                        decoder: Box::new(BufReader::new(GzDecoder::new(r))),
+                        offset,
+                    };
+                }
+                Source::GzMember {
+                    mut decoder,
+                    offset,
+                } => {
+                    let context = || format!("Malformed record in gzip member at offset {offset}");
+                    let record = read_record(&mut *decoder).with_context(context)?;
+                    let member_end = decoder.fill_buf().with_context(context)?.is_empty();
+                    if !member_end {
+                        self.source = Source::GzMember { decoder, offset };
+                        return Ok(record.map(|record| ReadRecord {
+                            record,
+                            offset,
+                            length: None,
+                        }));
+                    }
+                    let mut r = decoder.into_inner().into_inner();
+                    let length = r.stream_position()? - offset;
+                    self.source = Source::Gz(r);
+                    if let Some(record) = record {
+                        return Ok(Some(ReadRecord {
+                            record,
+                            offset,
+                            length: Some(length),
+                        }));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<R: BufRead + Seek> Iterator for Records<R> {
+    type Item = Result<ReadRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+/// Reads one record. Returns None at the end of the input.
+fn read_record(r: &mut impl BufRead) -> Result<Option<Record>> {
+    let mut line = Vec::new();
+    if r.read_until(b'\n', &mut line)? == 0 {
+        return Ok(None);
+    }
+    if !line.starts_with(b"WARC/") {
+        bail!("Not a WARC record: {:?}", String::from_utf8_lossy(&line));
+    }
+
+    let mut record = Record::new("", SystemTime::UNIX_EPOCH);
+    let mut content_length = None;
+    loop {
+        line.clear();
+        if r.read_until(b'\n', &mut line)? == 0 {
+            bail!("Unexpected end of header");
+        }
+        let line = std::str::from_utf8(&line)?.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid header line: {line:?}"))?;
+        let value = value.trim();
+        match name.to_ascii_lowercase().as_str() {
+            "warc-type" => record.warc_type = value.to_string(),
+            "warc-record-id" => {
+                record.id = value.trim_matches(['<', '>']).to_string();
+            }
+            "warc-date" => record.date = DateTime::parse_from_rfc3339(value)?.into(),
+            "content-length" => {
+                content_length = Some(value.parse::<u64>()?);
+                record.fields.push((name.to_string(), value.to_string()));
+            }
+            _ => record.fields.push((name.to_string(), value.to_string())),
+        }
+    }
+    if record.warc_type.is_empty() {
+        bail!("Missing WARC-Type");
+    }
+
+    let content_length = content_length.ok_or_else(|| anyhow!("Missing Content-Length"))?;
+    r.take(content_length).read_to_end(&mut record.block)?;
+    if record.block.len() as u64 != content_length {
+        bail!(
+            "Block of {} bytes is shorter than Content-Length {content_length}",
+            record.block.len()
+        );
+    }
+    let mut end = [0; 4];
+    r.read_exact(&mut end)
+        .context("Missing CRLF CRLF after the block")?;
+    if end != *b"\r\n\r\n" {
+        bail!("Block does not end after Content-Length {content_length}");
+    }
+    Ok(Some(record))
+}
+
+/// HTTP response in the block of a response or revisit record
+pub struct HttpResponse<'a> {
+    pub status: u16,
+    pub headers: Vec<(&'a str, &'a str)>,
+    pub body: &'a [u8],
+}
+
+impl<'a> HttpResponse<'a> {
+    pub fn parse(block: &'a [u8]) -> Result<Self> {
+        let header_end = block
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .ok_or_else(|| anyhow!("End of HTTP header not found"))?;
+        let header = std::str::from_utf8(&block[..header_end])?;
+        let mut lines = header.split("\r\n");
+        let status_line = lines.next().unwrap_or_default();
+        let status = status_line
+            .split(' ')
+            .nth(1)
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| anyhow!("Invalid status line: {status_line:?}"))?;
+        let headers = lines
+            .filter_map(|l| l.split_once(':'))
+            .map(|(name, value)| (name, value.trim()))
+            .collect();
+        Ok(Self {
+            status,
+            headers,
+            body: &block[header_end + 4..],
+        })
+    }
+
+    /// Value of the first header with the given name, ignoring case
+    pub fn header(&self, name: &str) -> Option<&'a str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| *v)
+    }
+
+    /// Media type of the `Content-Type` header without parameters
+    pub fn mime(&self) -> Option<&'a str> {
+        self.header("content-type")
+            .and_then(|v| v.split(';').next())
+            .map(str::trim)
+    }
+}
+
+/// Checks `WARC-Block-Digest` and, for response records, the
+/// `WARC-Payload-Digest`. Only SHA-1 digests are checked.
+pub fn validate(record: &Record) -> Result<()> {
+    if let Some(expected) = record.get("WARC-Block-Digest") {
+        check_digest("Block", expected, &record.block)?;
+    }
+    if record.warc_type == "response" {
+        if let Some(expected) = record.get("WARC-Payload-Digest") {
+            let response = HttpResponse::parse(&record.block)?;
+            check_digest("Payload", expected, response.body)?;
+        }
+    }
+    Ok(())
+}
+
+fn check_digest(what: &str, expected: &str, data: &[u8]) -> Result<()> {
+    if !expected.to_ascii_lowercase().starts_with("sha1:") {
+        return Ok(());
+    }
+    let actual = digest(data);
+    if !actual.eq_ignore_ascii_case(expected) {
+        bail!("{what} digest {actual} does not match {expected}");
+    }
+    Ok(())
+}
+
+/// Command `verify [WARC_FILE...]`: Reads all records of the given WARC
+/// files, by default of all files in `ARCHIVE_DIR`, and reports malformed
+/// records and wrong digests.
+pub fn verify(args: &[String]) -> Result<()> {
+    let mut invalid = 0;
+    for path in super::archive_files(args)? {
+        let (mut cnt, invalid_before) = (0, invalid);
+        for item in open(&path)? {
+            match item {
+                Ok(r) => {
+                    cnt += 1;
+                    if let Err(e) = validate(&r.record) {
+                        warn!("{}: record at offset {}: {e}", path.display(), r.offset);
+                        invalid += 1;
+                    }
+                }
+                Err(e) => {
+                    error!("{}: {e:#}", path.display());
+                    invalid += 1;
+                }
+            }
+        }
+        info!(
+            "{}: {cnt} records, {} invalid",
+            path.display(),
+            invalid - invalid_before
+        );
+    }
+    if invalid > 0 {
+        bail!("Found {invalid} invalid records");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate, HttpResponse, Records};
+    use crate::warc::{digest, Record};
+    use std::io::Cursor;
+    use std::time::SystemTime;
+
+    fn response(body: &str) -> Record {
+        let block = format!("HTTP/1.1 200 OK\r\ncontent-type: text/html\r\n\r\n{body}");
+        let mut record = Record::new("response", SystemTime::UNIX_EPOCH)
+            .field("WARC-Target-URI", "https://example.org/")
+            .field("WARC-Payload-Digest", digest(body.as_bytes()));
+        record.block = block.into_bytes();
+        record
+    }
+
+    #[test]
+    fn read_gzip_members() {
+        let (a, b) = (response("a"), response("bb"));
+        let mut file = a.to_gzip_member().unwrap();
+        let first_len = file.len() as u64;
+        file.extend(b.to_gzip_member().unwrap());
+
+        let records: Vec<_> = Records::gzip(Cursor::new(file))
+            .collect::<anyhow::Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].record.id, a.id);
+        assert_eq!(records[0].length, Some(first_len));
+        assert_eq!(records[1].offset, first_len);
+        assert_eq!(records[1].record.block, b.block);
+        for r in &records {
+            validate(&r.record).unwrap();
+            let http = HttpResponse::parse(&r.record.block).unwrap();
+            assert_eq!((http.status, http.mime()), (200, Some("text/html")));
+        }
+    }
+
+    #[test]
+    fn read_plain() {
+        let mut file = Vec::new();
+        response("a").write_to(&mut file).unwrap();
+        let len = file.len() as u64;
+        response("b").write_to(&mut file).unwrap();
+
+        let records: Vec<_> = Records::plain(Cursor::new(file))
+            .collect::<anyhow::Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[1].offset, records[1].length), (len, Some(len)));
+    }
+
+    #[test]
+    fn report_malformed() {
+        let mut file = Vec::new();
+        response("a").write_to(&mut file).unwrap();
+        let len = file.len();
+        let mut broken = response("b");
+        broken.block.truncate(10);
+        broken.write_to(&mut file).unwrap();
+        // Content-Length claims one byte more than the block has
+        let text = String::from_utf8(file).unwrap();
+        let text = text.replacen("Content-Length: 10", "Content-Length: 11", 1);
+
+        let mut records = Records::plain(Cursor::new(text.into_bytes()));
+        assert!(records.next().unwrap().is_ok());
+        let e = records.next().unwrap().err().unwrap();
+        assert_eq!(format!("{e}"), format!("Malformed record at offset {len}"));
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn wrong_digest() {
+        let mut record = response("a");
+        record.block = b"HTTP/1.1 200 OK\r\n\r\nb".to_vec();
+        let e = validate(&record).unwrap_err();
+        assert!(e.to_string().starts_with("Payload digest"));
+    }
+}
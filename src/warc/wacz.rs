@@ -0,0 +1,380 @@
+//! Exports the WARC files of a crawl job as WACZ file, a zip file that
+//! ReplayWeb.page and other tools can open:
+//! <https://specs.webrecorder.net/wacz/1.1.1/>
+//!
+//! - `archive/`: the WARC files of the job
+//! - `indexes/index.cdxj`: CDXJ index of the WARC files
+//! - `pages/pages.jsonl`: the archived HTML pages with their titles, seeds
+//!   first
+//! - `datapackage.json`: the files with their SHA-256 hashes
+//! - `datapackage-digest.json`: the hash of `datapackage.json`
+//!
+//! Payloads of revisit records that were archived by another job are copied
+//! into an additional WARC file to make the WACZ file self-contained.
+
+use super::cdxj::{self, Capture};
+use super::reader::{self, HttpResponse};
+use super::{Record, WarcLocation, FILE_PREFIX, SOFTWARE};
+use crate::crawl_job::CrawlJob;
+use crate::db;
+use crate::env_config::ARCHIVE_DIR;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use data_encoding::HEXLOWER;
+use select::document::Document;
+use select::predicate::Name;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use url::Url;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const WACZ_VERSION: &str = "1.1.1";
+
+/// An archived HTML page
+struct Page {
+    url: String,
+    date: SystemTime,
+    title: Option<String>,
+}
+
+/// Command `export-wacz JOB_ID [FILE]`: Writes the WACZ file of the crawl
+/// job, by default to `crawl-job-{JOB_ID}.wacz`.
+pub fn export(args: &[String]) -> Result<()> {
+    let [job_id, rest @ ..] = args else {
+        bail!("Usage: export-wacz JOB_ID [FILE]");
+    };
+    let job_id: i32 = job_id.parse()?;
+    let output = rest.first().map_or_else(
+        || PathBuf::from(format!("crawl-job-{job_id}.wacz")),
+        PathBuf::from,
+    );
+    let job: CrawlJob = db::select_crawl_job(&mut db::init_conn()?, job_id)?
+        .ok_or_else(|| anyhow!("No crawl job {job_id}"))?
+        .into();
+    let dir = ARCHIVE_DIR.parse::<PathBuf>();
+    let files = job_files(&dir, job_id)?;
+    if files.is_empty() {
+        bail!("No WARC files of crawl job {job_id} in {}", dir.display());
+    }
+    let originals_name = format!("{FILE_PREFIX}-{job_id}-originals.warc.gz");
+    let Contents {
+        index,
+        mut pages,
+        originals,
+    } = read_files(&dir, &files, &originals_name)?;
+    let seeds: Vec<String> = job.seeds.iter().map(Url::to_string).collect();
+    pages.sort_by_key(|p| (!seeds.contains(&p.url), p.date));
+
+    let mut wacz = Wacz::create(&output)?;
+    for path in &files {
+        let file = File::open(path)?;
+        let large = file.metadata()?.len() >= u64::from(u32::MAX);
+        wacz.add(&format!("archive/{}", file_name(path)), file, false, large)?;
+    }
+    if !originals.is_empty() {
+        wacz.add(
+            &format!("archive/{originals_name}"),
+            originals.as_slice(),
+            false,
+            false,
+        )?;
+    }
+    let mut index = index.join("\n");
+    index.push('\n');
+    wacz.add("indexes/index.cdxj", index.as_bytes(), true, false)?;
+    wacz.add(
+        "pages/pages.jsonl",
+        pages_jsonl(&pages, &seeds).as_bytes(),
+        true,
+        false,
+    )?;
+    wacz.finish(&job)?;
+    info!(
+        "Exported {} WARC files and {} pages of crawl job {job_id} to {}",
+        files.len(),
+        pages.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// Index lines, pages and the copied payloads of revisit records of the
+/// WARC files of a job
+struct Contents {
+    index: Vec<String>,
+    pages: Vec<Page>,
+    /// Gzip members of the additional WARC file
+    originals: Vec<u8>,
+}
+
+fn read_files(dir: &Path, files: &[PathBuf], originals_name: &str) -> Result<Contents> {
+    let mut index = Vec::new();
+    let mut pages = Vec::new();
+    let mut revisits = Vec::new();
+    for path in files {
+        let filename = file_name(path);
+        for item in reader::open(path)? {
+            let item = item.with_context(|| path.display().to_string())?;
+            let Some(length) = item.length else {
+                warn!(
+                    "{}: Skipping record in gzip member at {} with more records",
+                    path.display(),
+                    item.offset
+                );
+                continue;
+            };
+            let location = WarcLocation {
+                filename: filename.clone(),
+                offset: item.offset,
+                length,
+            };
+            index.extend(cdxj::line(&item.record, &location));
+            match item.record.warc_type.as_str() {
+                "response" => pages.extend(page(&item.record, &item.record)),
+                "revisit" => revisits.push(item.record),
+                _ => (),
+            }
+        }
+    }
+
+    // Payloads of revisit records
+    let mut originals = Vec::new();
+    let mut copied = HashSet::new();
+    for revisit in &revisits {
+        let Some(capture) = original(revisit)? else {
+            continue;
+        };
+        let record = reader::read_at(dir, &capture.location)?;
+        pages.extend(page(revisit, &record));
+        if files
+            .iter()
+            .any(|f| file_name(f) == capture.location.filename)
+            || !copied.insert((capture.location.filename.clone(), capture.location.offset))
+        {
+            continue;
+        }
+        let location = WarcLocation {
+            filename: originals_name.to_string(),
+            offset: originals.len() as u64,
+            length: capture.location.length,
+        };
+        copy_member(dir, &capture.location, &mut originals)?;
+        index.extend(cdxj::line(&record, &location));
+    }
+
+    index.sort_unstable();
+    Ok(Contents {
+        index,
+        pages,
+        originals,
+    })
+}
+
+/// The WARC files of the crawl job, named `lara-{job_id}-...`
+fn job_files(dir: &Path, job_id: i32) -> Result<Vec<PathBuf>> {
+    let prefix = format!("{FILE_PREFIX}-{job_id}-");
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = file_name(&path);
+        if name.starts_with(&prefix) && name.ends_with(".warc.gz") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+/// The capture holding the payload of an identical-payload-digest revisit
+fn original(revisit: &Record) -> Result<Option<Capture>> {
+    let (Some(uri), Some(date)) = (
+        revisit.get("WARC-Refers-To-Target-URI"),
+        revisit.get("WARC-Refers-To-Date"),
+    ) else {
+        return Ok(None);
+    };
+    let timestamp = cdxj::timestamp(DateTime::parse_from_rfc3339(date)?.into());
+    let original = cdxj::captures(&Url::parse(uri)?)?
+        .into_iter()
+        .find(|c| c.timestamp == timestamp && !c.is_revisit());
+    if original.is_none() {
+        warn!("Payload of {uri} from {date} not found in the index");
+    }
+    Ok(original)
+}
+
+/// Copies the gzip member holding a record
+fn copy_member(dir: &Path, location: &WarcLocation, to: &mut Vec<u8>) -> Result<()> {
+    let mut file = File::open(dir.join(&location.filename))?;
+    file.seek(SeekFrom::Start(location.offset))?;
+    file.take(location.length).read_to_end(to)?;
+    Ok(())
+}
+
+/// The page of a response record or of a revisit record with the payload
+/// in the response record `payload`, if it is a successful HTML response
+fn page(record: &Record, payload: &Record) -> Option<Page> {
+    let http = HttpResponse::parse(&payload.block).ok()?;
+    if http.status != 200 || http.mime() != Some("text/html") {
+        return None;
+    }
+    let document = Document::from(String::from_utf8_lossy(http.body).as_ref());
+    let title = document
+        .find(Name("title"))
+        .next()
+        .map(|n| n.text().split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|t| !t.is_empty());
+    Some(Page {
+        url: record.get("WARC-Target-URI")?.to_string(),
+        date: record.date,
+        title,
+    })
+}
+
+fn pages_jsonl(pages: &[Page], seeds: &[String]) -> String {
+    let mut lines = vec![json!({
+        "format": "json-pages-1.0",
+        "id": "pages",
+        "title": "All Pages",
+    })];
+    for page in pages {
+        let mut line = json!({
+            "url": page.url,
+            "ts": iso_date(page.date),
+        });
+        if let Some(title) = &page.title {
+            line["title"] = json!(title);
+        }
+        if seeds.contains(&page.url) {
+            line["seed"] = json!(true);
+        }
+        lines.push(line);
+    }
+    let mut jsonl = lines
+        .iter()
+        .map(Value::to_string)
+        .collect::<Vec<_>>()
+        .join("\n");
+    jsonl.push('\n');
+    jsonl
+}
+
+fn iso_date(date: SystemTime) -> String {
+    DateTime::<Utc>::from(date).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Zip file being written with the resources for `datapackage.json`
+struct Wacz {
+    zip: ZipWriter<File>,
+    resources: Vec<Value>,
+}
+
+impl Wacz {
+    fn create(path: &Path) -> Result<Self> {
+        Ok(Self {
+            zip: ZipWriter::new(
+                File::create_new(path).with_context(|| path.display().to_string())?,
+            ),
+            resources: Vec::new(),
+        })
+    }
+
+    /// Adds a file and hashes it. Gzipped WARC files are not compressed again.
+    fn add(&mut self, path: &str, mut data: impl Read, compress: bool, large: bool) -> Result<()> {
+        let method = if compress {
+            CompressionMethod::Deflated
+        } else {
+            CompressionMethod::Stored
+        };
+        let options = SimpleFileOptions::default()
+            .compression_method(method)
+            .large_file(large);
+        self.zip.start_file(path, options)?;
+        let mut hasher = Sha256::new();
+        let mut bytes = 0;
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            let n = data.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            self.zip.write_all(&buf[..n])?;
+            bytes += n as u64;
+        }
+        self.resources.push(json!({
+            "name": path.rsplit('/').next(),
+            "path": path,
+            "hash": format!("sha256:{}", HEXLOWER.encode(&hasher.finalize())),
+            "bytes": bytes,
+        }));
+        Ok(())
+    }
+
+    /// Writes `datapackage.json` and `datapackage-digest.json`
+    fn finish(mut self, job: &CrawlJob) -> Result<()> {
+        let seeds: Vec<String> = job.seeds.iter().map(Url::to_string).collect();
+        let datapackage = json!({
+            "profile": "data-package",
+            "wacz_version": WACZ_VERSION,
+            "title": format!("Crawl job {}", job.id),
+            "description": format!("Seeds: {}", seeds.join(" ")),
+            "created": iso_date(SystemTime::now()),
+            "software": SOFTWARE,
+            "resources": self.resources,
+        });
+        let datapackage = serde_json::to_string_pretty(&datapackage)?;
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        self.zip.start_file("datapackage.json", options)?;
+        self.zip.write_all(datapackage.as_bytes())?;
+
+        let digest = json!({
+            "path": "datapackage.json",
+            "hash": format!("sha256:{}", HEXLOWER.encode(&Sha256::digest(&datapackage))),
+        });
+        self.zip.start_file("datapackage-digest.json", options)?;
+        self.zip
+            .write_all(serde_json::to_string_pretty(&digest)?.as_bytes())?;
+        self.zip.finish()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{page, pages_jsonl};
+    use crate::warc::Record;
+    use std::time::SystemTime;
+
+    #[test]
+    fn html_pages() {
+        let mut record = Record::new("response", SystemTime::UNIX_EPOCH)
+            .field("WARC-Target-URI", "https://example.org/");
+        record.block = b"HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\r\n\
+            <html><head><title>\n  Hello\n  World </title></head></html>"
+            .to_vec();
+        let page = page(&record, &record).unwrap();
+        assert_eq!(page.title.as_deref(), Some("Hello World"));
+        assert_eq!(
+            pages_jsonl(&[page], &["https://example.org/".to_string()]),
+            "{\"format\":\"json-pages-1.0\",\"id\":\"pages\",\"title\":\"All Pages\"}\n\
+             {\"seed\":true,\"title\":\"Hello World\",\"ts\":\"1970-01-01T00:00:00Z\",\"url\":\"https://example.org/\"}\n"
+        );
+
+        record.block = b"HTTP/1.1 200 OK\r\nContent-Type: image/png\r\n\r\n".to_vec();
+        assert!(super::page(&record, &record).is_none());
+    }
+}